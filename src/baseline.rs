@@ -0,0 +1,134 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+use serde::{Deserialize, Serialize};
+
+use crate::ignore::site_path;
+use crate::report::{Problem, ProblemKind};
+
+/// A known problem, identified by the document it occurs in and the href, such that it still
+/// matches after unrelated changes to the document.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    /// Relative to the static file path, with forward slashes.
+    pub path: String,
+    pub kind: ProblemKind,
+    pub href: String,
+}
+
+impl fmt::Display for BaselineEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} {}", self.path, self.kind.id(), self.href)
+    }
+}
+
+/// Problems that were accepted when hyperlink was introduced to a site, and are not reported
+/// again. Written with `--write-baseline`, read with `--baseline`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Baseline {
+    #[serde(skip)]
+    base_path: PathBuf,
+    problems: BTreeSet<BaselineEntry>,
+    /// Entries that were found again in this run. All others have been fixed.
+    #[serde(skip)]
+    matched: BTreeSet<BaselineEntry>,
+}
+
+impl Baseline {
+    pub fn new(base_path: PathBuf) -> Self {
+        Baseline {
+            base_path,
+            ..Default::default()
+        }
+    }
+
+    pub fn load(path: &Path, base_path: PathBuf) -> Result<Self, Error> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read baseline {}", path.display()))?;
+        let mut baseline: Baseline = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse baseline {}", path.display()))?;
+        baseline.base_path = base_path;
+        Ok(baseline)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let mut contents = serde_json::to_string_pretty(self)?;
+        contents.push('\n');
+        fs::write(path, contents)
+            .with_context(|| format!("Failed to write baseline {}", path.display()))
+    }
+
+    pub fn entries(&self) -> &BTreeSet<BaselineEntry> {
+        &self.problems
+    }
+
+    fn entry(&self, problem: &Problem) -> BaselineEntry {
+        BaselineEntry {
+            path: site_path(&self.base_path, &problem.path),
+            kind: problem.kind,
+            href: problem.href.clone(),
+        }
+    }
+
+    pub fn insert(&mut self, problem: &Problem) {
+        let entry = self.entry(problem);
+        self.problems.insert(entry);
+    }
+
+    /// Whether the problem is in the baseline. Also marks the entry as not fixed.
+    pub fn matches(&mut self, problem: &Problem) -> bool {
+        let entry = self.entry(problem);
+        if self.problems.contains(&entry) {
+            self.matched.insert(entry);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Entries that did not match any problem, and can be removed from the baseline.
+    pub fn fixed(&self) -> impl Iterator<Item = &BaselineEntry> {
+        self.problems.difference(&self.matched)
+    }
+}
+
+#[test]
+fn test_baseline() {
+    use std::sync::Arc;
+
+    let problem = |path: &str, href: &str| Problem {
+        kind: ProblemKind::BadLink,
+        href: href.to_owned(),
+        details: None,
+        path: Arc::new(PathBuf::from(path)),
+        lineno: None,
+        sources: Vec::new(),
+        suggestions: Vec::new(),
+    };
+
+    let mut baseline = Baseline::new(PathBuf::from("public"));
+    baseline.insert(&problem("public/index.html", "missing.html"));
+    baseline.insert(&problem("public/index.html", "gone.html"));
+    baseline.insert(&problem("public/index.html", "gone.html"));
+    assert_eq!(baseline.entries().len(), 2);
+
+    let dir = assert_fs::TempDir::new().unwrap();
+    let path = dir.path().join("baseline.json");
+    baseline.save(&path).unwrap();
+
+    // The static file path may be given differently on later runs.
+    let mut baseline = Baseline::load(&path, PathBuf::from("./public")).unwrap();
+    assert!(baseline.matches(&problem("./public/index.html", "missing.html")));
+    assert!(baseline.matches(&problem("./public/index.html", "missing.html")));
+    assert!(!baseline.matches(&problem("./public/about.html", "missing.html")));
+
+    let fixed: Vec<_> = baseline.fixed().map(ToString::to_string).collect();
+    assert_eq!(fixed, vec!["index.html: bad-link gone.html".to_owned()]);
+
+    fs::write(&path, "{}").unwrap();
+    assert!(Baseline::load(&path, PathBuf::new()).is_err());
+}
@@ -1,9 +1,12 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use patricia_tree::PatriciaMap;
 
-use crate::html::{Href, Link, UsedLink};
+use crate::hosting::{HostingConfig, TrailingSlash};
+use crate::html::{Href, Link, LinkTag, UsedLink};
+use crate::suggestions::{suggest_anchors, suggest_hrefs};
 
 impl<'a> AsRef<[u8]> for Href<'a> {
     fn as_ref(&self) -> &[u8] {
@@ -22,6 +25,7 @@ pub struct OwnedUsedLink<P> {
     pub href: String,
     pub path: Arc<PathBuf>,
     pub paragraph: Option<P>,
+    pub ignored: bool,
 }
 
 /// Collects only used links for match-all-paragraphs command. Discards defined links.
@@ -42,6 +46,7 @@ impl<P: Send> LinkCollector<P> for UsedLinkCollector<P> {
                 href: used_link.href.0.to_owned(),
                 path: used_link.path.to_owned(),
                 paragraph: used_link.paragraph,
+                ignored: used_link.ignored,
             });
         }
     }
@@ -51,19 +56,129 @@ impl<P: Send> LinkCollector<P> for UsedLinkCollector<P> {
     }
 }
 
+/// Collects the amount of links, all distinct hrefs, and which hrefs each document links to for
+/// the stats subcommand.
+pub struct StatsCollector {
+    pub used_link_count: usize,
+    pub hrefs: BTreeSet<String>,
+    /// Distinct hrefs without anchor by the document they are used in.
+    pub links: BTreeSet<(Arc<PathBuf>, String)>,
+}
+
+impl<P: Send> LinkCollector<P> for StatsCollector {
+    fn new() -> Self {
+        StatsCollector {
+            used_link_count: 0,
+            hrefs: BTreeSet::new(),
+            links: BTreeSet::new(),
+        }
+    }
+
+    fn ingest<'a>(&mut self, link: Link<'a, P>) {
+        if let Link::Uses(used_link) = link {
+            self.used_link_count += 1;
+            self.links
+                .insert((used_link.path, used_link.href.without_anchor().0.to_owned()));
+            if !self.hrefs.contains(used_link.href.0) {
+                self.hrefs.insert(used_link.href.0.to_owned());
+            }
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.used_link_count += other.used_link_count;
+        self.hrefs.extend(other.hrefs);
+        self.links.extend(other.links);
+    }
+}
+
+/// Collects the hrefs of all files and anchors, and all links between them for the graph
+/// subcommand.
+pub struct GraphCollector {
+    pub defined: BTreeSet<String>,
+    /// Amount of links by the document they are used in, their href, and the element they are
+    /// used in.
+    pub edges: BTreeMap<(Arc<PathBuf>, String, LinkTag), usize>,
+}
+
+impl<P: Send> LinkCollector<P> for GraphCollector {
+    fn new() -> Self {
+        GraphCollector {
+            defined: BTreeSet::new(),
+            edges: BTreeMap::new(),
+        }
+    }
+
+    fn ingest<'a>(&mut self, link: Link<'a, P>) {
+        match link {
+            Link::Uses(used_link) => {
+                *self
+                    .edges
+                    .entry((used_link.path, used_link.href.0.to_owned(), used_link.tag))
+                    .or_insert(0) += 1;
+            }
+            Link::UsesExternal(_) => (),
+            Link::Defines(defined_link) => {
+                self.defined.insert(defined_link.href.0.to_owned());
+            }
+        }
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.defined.extend(other.defined);
+        for (edge, count) in other.edges {
+            *self.edges.entry(edge).or_insert(0) += count;
+        }
+    }
+}
+
+/// Wraps another collector and remembers the targets of all used links, such that they can be
+/// followed when crawling the site.
+pub struct CrawlCollector<C> {
+    pub collector: C,
+    /// Hrefs of used links, without anchor.
+    pub targets: Vec<String>,
+}
+
+impl<P: Send, C: LinkCollector<P>> LinkCollector<P> for CrawlCollector<C> {
+    fn new() -> Self {
+        CrawlCollector {
+            collector: C::new(),
+            targets: Vec::new(),
+        }
+    }
+
+    fn ingest<'a>(&mut self, link: Link<'a, P>) {
+        if let Link::Uses(ref used_link) = link {
+            self.targets
+                .push(used_link.href.without_anchor().0.to_owned());
+        }
+        self.collector.ingest(link);
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.collector.merge(other.collector);
+        self.targets.extend(other.targets);
+    }
+}
+
+/// All places a link is used in: the file, the paragraph within that file, and whether problems
+/// are ignored at that place.
+type LinkUsages<P> = Vec<(Arc<PathBuf>, Option<P>, bool)>;
+
 #[derive(Debug)]
 enum LinkState<P> {
     /// We have observed a DefinedLink for this href
     Defined,
     /// We have not *yet* observed a DefinedLink and therefore need to keep track of all link
     /// usages for potential error reporting.
-    Undefined(Vec<(Arc<PathBuf>, Option<P>)>),
+    Undefined(LinkUsages<P>),
 }
 
 impl<P: Copy> LinkState<P> {
     fn add_usage(&mut self, link: &UsedLink<P>) {
         if let LinkState::Undefined(ref mut links) = self {
-            links.push((link.path.clone(), link.paragraph));
+            links.push((link.path.clone(), link.paragraph, link.ignored));
         }
     }
 
@@ -82,6 +197,9 @@ impl<P: Copy> LinkState<P> {
 pub struct BrokenLinkCollector<P> {
     links: PatriciaMap<LinkState<P>>,
     used_link_count: usize,
+    /// All usages of external links, keyed by URL. Those can only be checked after all files have
+    /// been read, as every URL should only be requested once.
+    external_links: BTreeMap<String, LinkUsages<P>>,
 }
 
 impl<P: Send + Copy> LinkCollector<P> for BrokenLinkCollector<P> {
@@ -89,6 +207,7 @@ impl<P: Send + Copy> LinkCollector<P> for BrokenLinkCollector<P> {
         BrokenLinkCollector {
             links: PatriciaMap::new(),
             used_link_count: 0,
+            external_links: BTreeMap::new(),
         }
     }
 
@@ -104,6 +223,15 @@ impl<P: Send + Copy> LinkCollector<P> for BrokenLinkCollector<P> {
                     self.links.insert(used_link.href, state);
                 }
             }
+            Link::UsesExternal(used_link) => {
+                let usage = (used_link.path, used_link.paragraph, used_link.ignored);
+                if let Some(usages) = self.external_links.get_mut(used_link.href.0) {
+                    usages.push(usage);
+                } else {
+                    self.external_links
+                        .insert(used_link.href.0.to_owned(), vec![usage]);
+                }
+            }
             Link::Defines(defined_link) => {
                 self.links.insert(defined_link.href, LinkState::Defined);
             }
@@ -113,6 +241,13 @@ impl<P: Send + Copy> LinkCollector<P> for BrokenLinkCollector<P> {
     fn merge(&mut self, other: Self) {
         self.used_link_count += other.used_link_count;
 
+        for (url, usages) in other.external_links {
+            self.external_links
+                .entry(url)
+                .or_insert_with(Vec::new)
+                .extend(usages);
+        }
+
         for (href, other_state) in other.links {
             if let Some(state) = self.links.get_mut(&href) {
                 state.update(other_state);
@@ -123,36 +258,165 @@ impl<P: Send + Copy> LinkCollector<P> for BrokenLinkCollector<P> {
     }
 }
 
+/// Look up the file an href points to, with the same fallbacks as `get_broken_links` for clean
+/// URLs and trailing slashes, such that links that work are followed.
+pub fn find_target<T>(
+    href: &str,
+    hosting: &HostingConfig,
+    lookup: impl Fn(&str) -> Option<T>,
+) -> Option<T> {
+    lookup(href)
+        .or_else(|| lookup(&hosting.clean_url(href)?))
+        .or_else(|| lookup(&toggle_trailing_slash(href)?))
+}
+
+/// Add a trailing slash to the path of an href if it has none, remove it otherwise.
+fn toggle_trailing_slash(href: &str) -> Option<String> {
+    let path_end = href.find('#').unwrap_or_else(|| href.len());
+    let (path, anchor) = href.split_at(path_end);
+
+    if path.is_empty() {
+        None
+    } else if let Some(path) = path.strip_suffix('/') {
+        Some(format!("{}{}", path, anchor))
+    } else {
+        Some(format!("{}/{}", path, anchor))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum BrokenLinkKind {
+    /// The linked file does not exist.
+    Missing,
+    /// The linked file exists, but the anchor does not.
+    MissingAnchor,
+    /// The link works, but the site is served with clean URLs and the link should not use the
+    /// `.html` extension.
+    HtmlExtension,
+    /// The link only works with or without trailing slash, and uses the wrong form.
+    TrailingSlash,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
 pub struct BrokenLink<P> {
-    pub hard_404: bool,
+    pub kind: BrokenLinkKind,
     pub link: OwnedUsedLink<P>,
+    /// Existing hrefs the link may have been meant to point to, best match first.
+    pub suggestions: Vec<String>,
 }
 
 impl<P: Copy + PartialEq> BrokenLinkCollector<P> {
-    pub fn get_broken_links(&self, check_anchors: bool) -> impl Iterator<Item = BrokenLink<P>> {
+    /// All links to hrefs that are not defined, with up to `max_suggestions` hrefs each that
+    /// they may have been meant to point to.
+    pub fn get_broken_links(
+        &self,
+        check_anchors: bool,
+        hosting: &HostingConfig,
+        max_suggestions: usize,
+    ) -> impl Iterator<Item = BrokenLink<P>> {
         let mut broken_links = Vec::new();
 
+        // Candidates for suggestions: the hrefs of all files, and the anchors defined in each.
+        let mut files = Vec::new();
+        let mut anchors: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        if max_suggestions > 0 {
+            for (href, state) in self.links.iter() {
+                if let LinkState::Defined = state {
+                    let href = unsafe { String::from_utf8_unchecked(href) };
+                    if let Some(anchor_start) = href.find('#') {
+                        anchors
+                            .entry(href[..anchor_start].to_owned())
+                            .or_insert_with(Vec::new)
+                            .push(href[anchor_start + 1..].to_owned());
+                    } else {
+                        files.push(href);
+                    }
+                }
+            }
+        }
+
         for (href, state) in self.links.iter() {
             if let LinkState::Undefined(links) = state {
                 let href = unsafe { String::from_utf8_unchecked(href) };
-                let hard_404 = if check_anchors {
-                    !matches!(
-                        self.links.get(&Href(&href).without_anchor()),
-                        Some(&LinkState::Defined)
+
+                // The server may serve a redirect or rewrite there. There is no way to tell
+                // whether anchors are valid in that case.
+                if hosting.serves(Href(&href).without_anchor().0) {
+                    continue;
+                }
+
+                // With clean URLs, `foo.html` is served as `foo`.
+                let clean_url = hosting.clean_url(&href);
+
+                // With a trailing slash policy, hrefs retain their trailing slash. The link
+                // works if the server did not care about the slash.
+                let toggled_slash = if hosting.trailing_slash == TrailingSlash::Ignore {
+                    None
+                } else {
+                    toggle_trailing_slash(&href)
+                };
+
+                let defined_file = if check_anchors {
+                    [Some(&href), clean_url.as_ref(), toggled_slash.as_ref()]
+                        .iter()
+                        .flatten()
+                        .copied()
+                        .map(|x| &x[..x.find('#').unwrap_or_else(|| x.len())])
+                        .find(|x| self.is_defined(x))
+                } else {
+                    None
+                };
+
+                let (kind, mut suggestions) = if matches!(clean_url, Some(ref x) if self.is_defined(x))
+                {
+                    if !hosting.enforce_clean_urls {
+                        continue;
+                    }
+                    (
+                        BrokenLinkKind::HtmlExtension,
+                        clean_url.into_iter().collect(),
+                    )
+                } else if matches!(toggled_slash, Some(ref x) if self.is_defined(x)) {
+                    (
+                        BrokenLinkKind::TrailingSlash,
+                        toggled_slash.into_iter().collect(),
+                    )
+                } else if let Some(defined_file) = defined_file {
+                    // Keep the path as written, only replace the anchor.
+                    let (path, anchor) =
+                        href.split_at(href.find('#').unwrap_or_else(|| href.len()));
+                    let candidates = anchors.get(defined_file).into_iter().flatten();
+                    let suggestions = suggest_anchors(
+                        anchor.trim_start_matches('#'),
+                        candidates.map(String::as_str),
+                        max_suggestions,
+                    );
+                    (
+                        BrokenLinkKind::MissingAnchor,
+                        suggestions
+                            .into_iter()
+                            .map(|anchor| format!("{}#{}", path, anchor))
+                            .collect(),
                     )
                 } else {
-                    true
+                    let candidates = files.iter().map(String::as_str);
+                    (
+                        BrokenLinkKind::Missing,
+                        suggest_hrefs(&href, candidates, max_suggestions),
+                    )
                 };
+                suggestions.truncate(max_suggestions);
 
-                for (path, paragraph) in links.iter() {
+                for (path, paragraph, ignored) in links.iter() {
                     broken_links.push(BrokenLink {
-                        hard_404,
+                        kind,
                         link: OwnedUsedLink {
                             path: path.clone(),
                             paragraph: *paragraph,
                             href: href.clone(),
+                            ignored: *ignored,
                         },
+                        suggestions: suggestions.clone(),
                     });
                 }
             }
@@ -161,7 +425,33 @@ impl<P: Copy + PartialEq> BrokenLinkCollector<P> {
         broken_links.into_iter()
     }
 
+    pub fn is_defined(&self, href: &str) -> bool {
+        matches!(self.links.get(href), Some(&LinkState::Defined))
+    }
+
     pub fn used_links_count(&self) -> usize {
         self.used_link_count
     }
+
+    /// All distinct external URLs that have been linked to.
+    pub fn external_urls(&self) -> impl Iterator<Item = &str> {
+        self.external_links.keys().map(String::as_str)
+    }
+
+    /// All places that link to the given external URL.
+    pub fn get_external_link_usages<'a>(
+        &'a self,
+        url: &'a str,
+    ) -> impl Iterator<Item = OwnedUsedLink<P>> + 'a {
+        self.external_links
+            .get(url)
+            .into_iter()
+            .flatten()
+            .map(move |(path, paragraph, ignored)| OwnedUsedLink {
+                href: url.to_owned(),
+                path: path.clone(),
+                paragraph: *paragraph,
+                ignored: *ignored,
+            })
+    }
 }
@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+
+use crate::hosting::TrailingSlash;
+use crate::report::{Format, ProblemKind, Severity};
+
+/// Name of the configuration file that is picked up automatically.
+static CONFIG_FILENAME: &str = "hyperlink.toml";
+
+/// Settings from `hyperlink.toml`. They have the same names as the command line options, which
+/// take precedence over them.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    pub base_path: Option<PathBuf>,
+    pub sources: Option<PathBuf>,
+    pub check_anchors: bool,
+    pub check_external: bool,
+    pub check_orphans: bool,
+    pub crawl: bool,
+    pub entry_points: Vec<String>,
+    pub external_cache: Option<PathBuf>,
+    pub clean_urls: bool,
+    pub enforce_clean_urls: bool,
+    pub trailing_slash: Option<TrailingSlash>,
+    pub base_url: Option<String>,
+    pub internal_domains: Vec<String>,
+    pub hosting_configs: Vec<PathBuf>,
+    pub ignore_hrefs: Vec<String>,
+    pub ignore_raw_hrefs: Vec<String>,
+    pub ignore_paths: Vec<String>,
+    pub baseline: Option<PathBuf>,
+    pub format: Option<Format>,
+    /// Extensions of files that are parsed for links, without leading dot.
+    pub html_extensions: Option<Vec<String>>,
+    /// Extensions of files in the sources folder that are matched up with HTML.
+    pub markdown_extensions: Option<Vec<String>>,
+    /// Overrides whether a kind of problem is reported as error or warning.
+    #[serde(deserialize_with = "deserialize_severity")]
+    pub severity: BTreeMap<ProblemKind, Severity>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut config: Config = toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        // Paths are relative to the configuration file, not to the working directory.
+        let config_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        for path in config
+            .base_path
+            .iter_mut()
+            .chain(config.sources.iter_mut())
+            .chain(config.external_cache.iter_mut())
+            .chain(config.hosting_configs.iter_mut())
+            .chain(config.baseline.iter_mut())
+        {
+            *path = config_dir.join(&path);
+        }
+
+        Ok(config)
+    }
+
+    /// Load the configuration file from a directory, if there is one.
+    pub fn discover(dir: &Path) -> Result<Option<Self>, Error> {
+        let path = dir.join(CONFIG_FILENAME);
+        if path.is_file() {
+            Ok(Some(Config::load(&path)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// TOML keys are always strings, so kinds of problems cannot be deserialized as enum directly.
+fn deserialize_severity<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<ProblemKind, Severity>, D::Error> {
+    BTreeMap::<String, Severity>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(kind, severity)| Ok((kind.parse().map_err(D::Error::custom)?, severity)))
+        .collect()
+}
+
+#[test]
+fn test_load() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let path = dir.path().join(CONFIG_FILENAME);
+    fs::write(
+        &path,
+        r#"
+base-path = "public"
+sources = "../src"
+check-anchors = true
+trailing-slash = "always"
+format = "sarif"
+html-extensions = ["html", "xhtml"]
+
+[severity]
+bad-anchor = "error"
+bad-link = "warning"
+"#,
+    )
+    .unwrap();
+
+    let config = Config::load(&path).unwrap();
+    assert_eq!(config.base_path, Some(dir.path().join("public")));
+    assert_eq!(config.sources, Some(dir.path().join("../src")));
+    assert!(config.check_anchors);
+    assert!(!config.check_external);
+    assert_eq!(config.trailing_slash, Some(TrailingSlash::Always));
+    assert_eq!(config.format, Some(Format::Sarif));
+    assert_eq!(
+        config.html_extensions,
+        Some(vec!["html".to_owned(), "xhtml".to_owned()])
+    );
+    assert_eq!(config.markdown_extensions, None);
+    assert_eq!(
+        config.severity.get(&ProblemKind::BadAnchor),
+        Some(&Severity::Error)
+    );
+    assert_eq!(
+        config.severity.get(&ProblemKind::BadLink),
+        Some(&Severity::Warning)
+    );
+
+    fs::write(&path, "check-anchor = true").unwrap();
+    assert!(Config::load(&path).is_err());
+
+    fs::write(&path, "[severity]\nbad-link = \"fatal\"").unwrap();
+    assert!(Config::load(&path).is_err());
+
+    fs::write(&path, "[severity]\nbad-links = \"error\"").unwrap();
+    assert!(Config::load(&path).is_err());
+}
@@ -0,0 +1,307 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Error};
+use rayon::prelude::*;
+
+/// Upper bound for the amount of threads making requests at the same time, across all hosts.
+static MAX_THREADS: usize = 64;
+
+static USER_AGENT: &str = concat!("hyperlink/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ExternalStatus {
+    /// The server responded with a successful status code, possibly after redirects.
+    Ok(u16),
+    /// The server responded with an error status code.
+    HttpError(u16),
+    /// There was no response at all, e.g. because of a DNS error or timeout.
+    Failed(String),
+}
+
+impl ExternalStatus {
+    fn from_status(status: u16) -> Self {
+        if status < 400 {
+            ExternalStatus::Ok(status)
+        } else {
+            ExternalStatus::HttpError(status)
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        matches!(self, ExternalStatus::Ok(_))
+    }
+
+    fn is_retryable(&self) -> bool {
+        match self {
+            ExternalStatus::Ok(_) => false,
+            ExternalStatus::HttpError(status) => *status == 429 || *status >= 500,
+            ExternalStatus::Failed(_) => true,
+        }
+    }
+}
+
+impl fmt::Display for ExternalStatus {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExternalStatus::Ok(status) | ExternalStatus::HttpError(status) => {
+                write!(fmt, "HTTP {}", status)
+            }
+            ExternalStatus::Failed(message) => message.fmt(fmt),
+        }
+    }
+}
+
+pub struct ExternalCheckerOptions {
+    /// How many requests may be in flight against a single host at the same time.
+    pub concurrency_per_host: usize,
+    /// How often to retry a request that failed in a way that may be temporary.
+    pub retries: usize,
+    pub timeout: Duration,
+    pub cache_path: Option<PathBuf>,
+    pub cache_ttl: Duration,
+}
+
+/// Checks external http(s) links by making requests against them.
+///
+/// Each URL is requested with HEAD first. If the server responds with an error status, a GET
+/// request is attempted as many servers do not implement HEAD properly.
+pub struct ExternalChecker {
+    agent: ureq::Agent,
+    options: ExternalCheckerOptions,
+}
+
+impl ExternalChecker {
+    pub fn new(options: ExternalCheckerOptions) -> Self {
+        let mut agent = ureq::agent();
+        agent.set("User-Agent", USER_AGENT);
+        ExternalChecker { agent, options }
+    }
+
+    /// Check all given URLs. URLs are expected to be deduplicated already.
+    pub fn check<'a>(
+        &self,
+        urls: impl Iterator<Item = &'a str>,
+    ) -> Result<BTreeMap<&'a str, ExternalStatus>, Error> {
+        let mut cache = match self.options.cache_path {
+            Some(ref path) => Cache::load(path.clone(), self.options.cache_ttl)?,
+            None => Cache::default(),
+        };
+
+        let mut results = BTreeMap::new();
+        let mut urls_by_host = BTreeMap::new();
+
+        for url in urls {
+            if let Some(status) = cache.get(url) {
+                results.insert(url, ExternalStatus::Ok(status));
+            } else {
+                urls_by_host
+                    .entry(get_host(url))
+                    .or_insert_with(Vec::new)
+                    .push(url);
+            }
+        }
+
+        // Split the URLs of each host into lanes that are processed sequentially. This bounds the
+        // amount of concurrent requests per host to the amount of lanes.
+        let mut lanes = Vec::new();
+        for (_host, urls) in urls_by_host {
+            let lanes_count = self.options.concurrency_per_host.max(1).min(urls.len());
+            let first_lane = lanes.len();
+            lanes.resize_with(first_lane + lanes_count, Vec::new);
+
+            for (i, url) in urls.into_iter().enumerate() {
+                lanes[first_lane + i % lanes_count].push(url);
+            }
+        }
+
+        if !lanes.is_empty() {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(lanes.len().min(MAX_THREADS))
+                .build()?;
+
+            let checked: Vec<(&str, ExternalStatus)> = pool.install(|| {
+                lanes
+                    .par_iter()
+                    .flat_map(|lane| {
+                        lane.iter()
+                            .map(|url| (*url, self.check_url(url)))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect()
+            });
+
+            for (url, status) in checked {
+                if let ExternalStatus::Ok(code) = status {
+                    cache.insert(url, code);
+                }
+                results.insert(url, status);
+            }
+        }
+
+        cache.save()?;
+
+        Ok(results)
+    }
+
+    fn check_url(&self, url: &str) -> ExternalStatus {
+        let mut attempt = 0;
+
+        loop {
+            // Connection errors and timeouts would only happen again with GET.
+            let (status, retry_after) = match self.request("HEAD", url) {
+                (ExternalStatus::HttpError(_), _) => self.request("GET", url),
+                result => result,
+            };
+
+            if attempt >= self.options.retries || !status.is_retryable() {
+                return status;
+            }
+
+            let backoff = Duration::from_millis(500 << attempt.min(6));
+            thread::sleep(retry_after.map_or(backoff, |delay| delay.max(backoff)));
+            attempt += 1;
+        }
+    }
+
+    /// Make a single request, returning its status and how long a server that responded with
+    /// `429 Too Many Requests` asked to wait before the next one.
+    fn request(&self, method: &str, url: &str) -> (ExternalStatus, Option<Duration>) {
+        let response = self
+            .agent
+            .request(method, url)
+            .timeout(self.options.timeout)
+            .call();
+
+        if let Some(error) = response.synthetic_error() {
+            return (ExternalStatus::Failed(error.to_string()), None);
+        }
+
+        let retry_after = match response.status() {
+            429 => response.header("Retry-After").and_then(parse_retry_after),
+            _ => None,
+        };
+        (ExternalStatus::from_status(response.status()), retry_after)
+    }
+}
+
+/// Parse the value of a `Retry-After` header given in seconds. HTTP dates are not supported, in
+/// which case the regular backoff is used.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse().ok().map(Duration::from_secs)
+}
+
+/// Extract the host (including port) from an absolute URL, lowercased.
+fn get_host(url: &str) -> String {
+    let rest = match url.find("://") {
+        Some(i) => &url[i + 3..],
+        None => url,
+    };
+    let end = rest
+        .find(&['/', '?', '#'][..])
+        .unwrap_or_else(|| rest.len());
+    let host = &rest[..end];
+    // strip userinfo
+    let host = &host[host.rfind('@').map(|i| i + 1).unwrap_or(0)..];
+    host.to_ascii_lowercase()
+}
+
+/// On-disk cache of successfully checked URLs.
+///
+/// The file contains one entry per line: `<unix timestamp>\t<status>\t<url>`. Only successful
+/// results are cached, such that broken links are rechecked on every run.
+#[derive(Default)]
+struct Cache {
+    path: Option<PathBuf>,
+    ttl: Duration,
+    now: u64,
+    entries: BTreeMap<String, (u64, u16)>,
+}
+
+impl Cache {
+    fn load(path: PathBuf, ttl: Duration) -> Result<Self, Error> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let mut entries = BTreeMap::new();
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(x) => x,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(
+                    Error::new(e).context(format!("Failed to read cache file {}", path.display()))
+                )
+            }
+        };
+
+        for line in contents.lines() {
+            let mut parts = line.splitn(3, '\t');
+            let entry = (|| {
+                let timestamp: u64 = parts.next()?.parse().ok()?;
+                let status: u16 = parts.next()?.parse().ok()?;
+                let url = parts.next()?;
+                Some((timestamp, status, url))
+            })();
+
+            match entry {
+                Some((timestamp, status, url)) if timestamp + ttl.as_secs() > now => {
+                    entries.insert(url.to_owned(), (timestamp, status));
+                }
+                // expired or invalid entry
+                _ => {}
+            }
+        }
+
+        Ok(Cache {
+            path: Some(path),
+            ttl,
+            now,
+            entries,
+        })
+    }
+
+    fn get(&self, url: &str) -> Option<u16> {
+        let (_timestamp, status) = self.entries.get(url)?;
+        Some(*status)
+    }
+
+    fn insert(&mut self, url: &str, status: u16) {
+        self.entries.insert(url.to_owned(), (self.now, status));
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        let path = match self.path {
+            Some(ref x) => x,
+            None => return Ok(()),
+        };
+
+        let mut contents = String::new();
+        for (url, (timestamp, status)) in &self.entries {
+            if timestamp + self.ttl.as_secs() > self.now {
+                contents.push_str(&format!("{}\t{}\t{}\n", timestamp, status, url));
+            }
+        }
+
+        fs::write(path, contents)
+            .with_context(|| format!("Failed to write cache file {}", path.display()))
+    }
+}
+
+#[test]
+fn test_get_host() {
+    assert_eq!(get_host("https://example.com"), "example.com");
+    assert_eq!(get_host("https://Example.com/foo?bar"), "example.com");
+    assert_eq!(get_host("http://localhost:8000#foo"), "localhost:8000");
+    assert_eq!(get_host("http://user:pw@example.com/"), "example.com");
+}
+
+#[test]
+fn test_parse_retry_after() {
+    assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+    assert_eq!(parse_retry_after(" 0 "), Some(Duration::from_secs(0)));
+    assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+}
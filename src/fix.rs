@@ -0,0 +1,325 @@
+use std::cmp::min;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Error};
+
+use crate::markdown::DocumentSource;
+use crate::report::Problem;
+
+/// Lines of context around changes in diffs.
+const DIFF_CONTEXT: usize = 3;
+
+/// Broken links in markdown sources that can be rewritten to their suggestion, for `--fix`.
+#[derive(Debug, Default)]
+pub struct Fixes {
+    /// The only suggestion for broken hrefs that have exactly one.
+    suggestions: BTreeMap<String, String>,
+    /// The line of the paragraph, the broken href and the suggestion, by markdown file.
+    links: BTreeMap<Arc<PathBuf>, BTreeSet<(usize, String, String)>>,
+}
+
+impl Fixes {
+    /// How many suggestions are needed for a broken href to tell whether it has exactly one.
+    pub const SUGGESTIONS: usize = 2;
+
+    /// Remember the suggestions for a broken href, of which there must be up to `SUGGESTIONS`.
+    /// They are independent of how many suggestions are reported.
+    pub fn add_suggestions(&mut self, href: &str, suggestions: &[String]) {
+        if let [suggestion] = suggestions {
+            self.suggestions.insert(href.to_owned(), suggestion.clone());
+        }
+    }
+
+    /// Remember the problem for fixing if there is no doubt where and how: it must come from a
+    /// single markdown paragraph and have exactly one suggestion.
+    pub fn add(&mut self, problem: &Problem) {
+        let suggestion = self.suggestions.get(&problem.href);
+        if let ([source], Some(suggestion)) = (&problem.sources[..], suggestion) {
+            if let Some(lineno) = source.lineno {
+                self.links.entry(source.path.clone()).or_default().insert((
+                    lineno,
+                    problem.href.clone(),
+                    suggestion.clone(),
+                ));
+            }
+        }
+    }
+
+    /// Rewrite the links in all markdown files that have fixable problems. Files are not written
+    /// yet, and only returned if at least one link could be rewritten.
+    pub fn apply(&self) -> Result<Vec<FixedFile>, Error> {
+        let mut files = Vec::new();
+
+        for (path, fixes) in &self.links {
+            let (contents, links) = DocumentSource::new(path.to_path_buf())
+                .links()
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+
+            let mut new_contents = String::with_capacity(contents.len());
+            let mut fixed_links = 0;
+            let mut offset = 0;
+            for link in links {
+                let new_dest = fixes
+                    .iter()
+                    .filter(|(lineno, _, _)| *lineno == link.lineno)
+                    .find_map(|(_, href, suggestion)| {
+                        rewrite_destination(&link.dest, href, suggestion)
+                    });
+
+                if let Some(new_dest) = new_dest {
+                    new_contents.push_str(&contents[offset..link.range.start]);
+                    new_contents.push_str(&new_dest);
+                    offset = link.range.end;
+                    fixed_links += 1;
+                }
+            }
+            new_contents.push_str(&contents[offset..]);
+
+            if fixed_links > 0 {
+                files.push(FixedFile {
+                    path: path.clone(),
+                    contents,
+                    new_contents,
+                    fixed_links,
+                });
+            }
+        }
+
+        Ok(files)
+    }
+}
+
+/// A markdown file with rewritten links.
+pub struct FixedFile {
+    pub path: Arc<PathBuf>,
+    contents: String,
+    new_contents: String,
+    pub fixed_links: usize,
+}
+
+impl FixedFile {
+    pub fn write(&self) -> Result<(), Error> {
+        fs::write(&*self.path, &self.new_contents)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+
+    /// The changes as unified diff. Links are rewritten in place, so lines are only ever
+    /// changed, never added or removed.
+    pub fn diff(&self) -> String {
+        let lines: Vec<&str> = self.contents.lines().collect();
+        let new_lines: Vec<&str> = self.new_contents.lines().collect();
+        debug_assert_eq!(lines.len(), new_lines.len());
+
+        let changed: Vec<usize> = (0..lines.len())
+            .filter(|&i| lines[i] != new_lines[i])
+            .collect();
+
+        // Group changes whose context overlaps into hunks of (first line, end line).
+        let mut hunks: Vec<(usize, usize)> = Vec::new();
+        for &i in &changed {
+            let start = i.saturating_sub(DIFF_CONTEXT);
+            let end = min(i + 1 + DIFF_CONTEXT, lines.len());
+            match hunks.last_mut() {
+                Some(hunk) if hunk.1 >= start => hunk.1 = end,
+                _ => hunks.push((start, end)),
+            }
+        }
+
+        let path = self.path.display();
+        let mut diff = format!("--- {}\n+++ {}\n", path, path);
+        for (start, end) in hunks {
+            let len = end - start;
+            diff.push_str(&format!(
+                "@@ -{},{} +{},{} @@\n",
+                start + 1,
+                len,
+                start + 1,
+                len
+            ));
+
+            let mut i = start;
+            while i < end {
+                if lines[i] == new_lines[i] {
+                    diff.push_str(&format!(" {}\n", lines[i]));
+                    i += 1;
+                    continue;
+                }
+
+                // Consecutive changed lines are shown as one block of removals and additions.
+                let block_end = (i..end).find(|&j| lines[j] == new_lines[j]).unwrap_or(end);
+                for line in &lines[i..block_end] {
+                    diff.push_str(&format!("-{}\n", line));
+                }
+                for line in &new_lines[i..block_end] {
+                    diff.push_str(&format!("+{}\n", line));
+                }
+                i = block_end;
+            }
+        }
+
+        diff
+    }
+}
+
+/// Rewrite the destination of a link as written in markdown, such that it points to
+/// `suggestion` instead of the broken `href` it was resolved to. Only the differing end of the
+/// path and the anchor are replaced, relative links stay relative.
+///
+/// Returns `None` if the destination does not obviously resolve to `href`, e.g. because the
+/// markdown links to `.md` files that are converted to `.html` when the site is built.
+fn rewrite_destination(dest: &str, href: &str, suggestion: &str) -> Option<String> {
+    let (href_path, href_anchor) = split_at_char(href, '#');
+    let (suggestion_path, suggestion_anchor) = split_at_char(suggestion, '#');
+    let (dest, mut dest_anchor) = split_at_char(dest, '#');
+    let (dest_path, dest_query) = split_at_char(dest, '?');
+
+    // Anchors are only part of the href if they are checked.
+    if !href_anchor.is_empty() {
+        if dest_anchor != href_anchor {
+            return None;
+        }
+        dest_anchor = suggestion_anchor;
+    }
+
+    // What is left of the path after leading `/`, `./` and `../`.
+    let mut relative_path = dest_path;
+    while let Some(rest) = relative_path
+        .strip_prefix("../")
+        .or_else(|| relative_path.strip_prefix("./"))
+        .or_else(|| relative_path.strip_prefix('/'))
+    {
+        relative_path = rest;
+    }
+    if !relative_path.is_empty()
+        && href_path != relative_path
+        && !href_path.ends_with(&format!("/{}", relative_path))
+    {
+        return None;
+    }
+
+    let mut new_dest = dest_path.to_owned();
+    if href_path != suggestion_path {
+        // The length of the common prefix of the paths, up to a path segment boundary.
+        let common_len = href_path
+            .char_indices()
+            .zip(suggestion_path.chars())
+            .take_while(|((_, a), b)| a == b)
+            .filter(|((_, a), _)| *a == '/')
+            .last()
+            .map_or(0, |((i, _), _)| i + 1);
+
+        let old_end = &href_path[common_len..];
+        let new_end = &suggestion_path[common_len..];
+        let kept = relative_path.strip_suffix(old_end)?;
+        if !kept.is_empty() && !kept.ends_with('/') {
+            return None;
+        }
+
+        new_dest.truncate(dest_path.len() - old_end.len());
+        new_dest.push_str(new_end);
+    }
+
+    new_dest.push_str(dest_query);
+    new_dest.push_str(dest_anchor);
+    Some(new_dest)
+}
+
+/// Split before the first occurrence of `c`, such that the second part starts with it.
+fn split_at_char(text: &str, c: char) -> (&str, &str) {
+    text.split_at(text.find(c).unwrap_or_else(|| text.len()))
+}
+
+#[test]
+fn test_rewrite_destination() {
+    let rewrite = |dest, href, suggestion| rewrite_destination(dest, href, suggestion);
+
+    assert_eq!(
+        rewrite(
+            "../docs/instal.html",
+            "docs/instal.html",
+            "docs/install.html"
+        ),
+        Some("../docs/install.html".to_owned())
+    );
+    assert_eq!(
+        rewrite(
+            "instal.html?v=2#usage",
+            "docs/instal.html",
+            "docs/install.html"
+        ),
+        Some("install.html?v=2#usage".to_owned())
+    );
+    assert_eq!(
+        rewrite("/faq.html", "faq.html", "help/faq.html"),
+        Some("/help/faq.html".to_owned())
+    );
+    assert_eq!(
+        rewrite("../docs", "docs", "docs/"),
+        Some("../docs/".to_owned())
+    );
+    assert_eq!(
+        rewrite(
+            "#instalation",
+            "guide/intro.html#instalation",
+            "guide/intro.html#installation"
+        ),
+        Some("#installation".to_owned())
+    );
+    assert_eq!(
+        rewrite(
+            "intro.html#usage",
+            "guide/intro.html#usage",
+            "guide/intro#usage"
+        ),
+        Some("intro#usage".to_owned())
+    );
+
+    // Resolves to a different href.
+    assert_eq!(rewrite("other/faq.html", "faq.html", "help/faq.html"), None);
+    // The directory that changes is not part of the relative link.
+    assert_eq!(
+        rewrite("instal.html", "docs/instal.html", "guide/install.html"),
+        None
+    );
+    // Markdown links to markdown files.
+    assert_eq!(
+        rewrite("instal.md", "docs/instal.html", "docs/install.html"),
+        None
+    );
+}
+
+#[test]
+fn test_diff() {
+    let file = FixedFile {
+        path: Arc::new(PathBuf::from("docs/index.md")),
+        contents: "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n".to_owned(),
+        new_contents: "1\n2\nthree\nfour\n5\n6\n7\n8\n9\n10\n11\n12\nthirteen\n".to_owned(),
+        fixed_links: 3,
+    };
+
+    assert_eq!(
+        file.diff(),
+        "--- docs/index.md
++++ docs/index.md
+@@ -1,7 +1,7 @@
+ 1
+ 2
+-3
+-4
++three
++four
+ 5
+ 6
+ 7
+@@ -10,4 +10,4 @@
+ 10
+ 11
+ 12
+-13
++thirteen
+"
+    );
+}
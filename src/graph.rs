@@ -0,0 +1,345 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Error};
+use serde::Serialize;
+
+use crate::collector::{find_target, GraphCollector};
+use crate::hosting::HostingConfig;
+use crate::html::Document;
+use crate::ignore::site_path;
+use crate::report::xml_escape;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GraphFormat {
+    /// Graphviz
+    Dot,
+    Graphml,
+    Json,
+}
+
+impl FromStr for GraphFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "dot" => Ok(GraphFormat::Dot),
+            "graphml" => Ok(GraphFormat::Graphml),
+            "json" => Ok(GraphFormat::Json),
+            _ => Err(anyhow!(
+                "Invalid graph format {}, expected one of: dot, graphml, json",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(into = "&'static str")]
+pub enum NodeKind {
+    /// HTML file
+    Document,
+    /// Any other file
+    Asset,
+    Anchor,
+    /// Target of a broken link
+    Missing,
+}
+
+impl From<NodeKind> for &'static str {
+    fn from(kind: NodeKind) -> Self {
+        kind.id()
+    }
+}
+
+impl NodeKind {
+    fn id(self) -> &'static str {
+        match self {
+            NodeKind::Document => "document",
+            NodeKind::Asset => "asset",
+            NodeKind::Anchor => "anchor",
+            NodeKind::Missing => "missing",
+        }
+    }
+
+    fn dot_attributes(self) -> &'static str {
+        match self {
+            NodeKind::Document => "shape=box",
+            NodeKind::Asset => "shape=ellipse",
+            NodeKind::Anchor => "shape=plaintext",
+            NodeKind::Missing => "shape=box, style=dashed, color=red",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Node {
+    /// The href of the node as absolute path, such as `/docs/install.html#usage`.
+    pub id: String,
+    pub kind: NodeKind,
+    /// The file within the static file path. For anchors, the file they are defined in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Edge {
+    pub source: String,
+    pub target: String,
+    pub element: &'static str,
+    pub attribute: &'static str,
+    /// How often the source links to the target from this kind of element.
+    pub count: usize,
+}
+
+/// All files of a site and the links between them.
+#[derive(Debug, Serialize)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+fn node_id(href: &str) -> String {
+    format!("/{}", href)
+}
+
+impl Graph {
+    pub fn new(
+        collector: GraphCollector,
+        files: &[Document],
+        documents: &[Arc<PathBuf>],
+        base_path: &Path,
+        hosting: &HostingConfig,
+    ) -> Self {
+        let documents: BTreeSet<&Path> = documents.iter().map(|path| path.as_path()).collect();
+
+        let mut nodes = BTreeMap::new();
+        let mut ids_by_path = BTreeMap::new();
+        let mut paths_by_href = BTreeMap::new();
+
+        for file in files {
+            let id = node_id(file.href().0);
+            let kind = if documents.contains(file.path.as_path()) {
+                NodeKind::Document
+            } else {
+                NodeKind::Asset
+            };
+            let path = site_path(base_path, &file.path);
+
+            ids_by_path.insert(file.path.clone(), id.clone());
+            paths_by_href.insert(file.href().0, path.clone());
+            nodes.insert(id.clone(), (kind, Some(path)));
+        }
+
+        let GraphCollector {
+            defined,
+            edges: links,
+        } = collector;
+
+        // Amount of links by source, target, element and attribute. Paths and hrefs of documents
+        // sort differently, e.g. with index.html, so edges are sorted by their ids.
+        let mut edges = BTreeMap::new();
+
+        // Anchors are contained in their document, which is expressed as an edge from the
+        // document to the element with that id.
+        for href in &defined {
+            if let Some(anchor_start) = href.find('#') {
+                let path = paths_by_href.get(&href[..anchor_start]).cloned();
+                if path.is_some() {
+                    let edge = (node_id(&href[..anchor_start]), node_id(href), "", "id");
+                    edges.insert(edge, 1);
+                }
+                nodes.insert(node_id(href), (NodeKind::Anchor, path));
+            }
+        }
+
+        for ((path, href, tag), count) in links {
+            let source = match ids_by_path.get(&path) {
+                Some(source) => source.clone(),
+                None => continue,
+            };
+            // Links that work because of clean URLs or trailing slash handling point to the file
+            // they are served by.
+            let href = find_target(&href, hosting, |href| defined.get(href))
+                .map_or(href.clone(), String::clone);
+            let target = node_id(&href);
+            nodes
+                .entry(target.clone())
+                .or_insert((NodeKind::Missing, None));
+
+            *edges
+                .entry((source, target, tag.element, tag.attribute))
+                .or_insert(0) += count;
+        }
+
+        Graph {
+            nodes: nodes
+                .into_iter()
+                .map(|(id, (kind, path))| Node { id, kind, path })
+                .collect(),
+            edges: edges
+                .into_iter()
+                .map(|((source, target, element, attribute), count)| Edge {
+                    source,
+                    target,
+                    element,
+                    attribute,
+                    count,
+                })
+                .collect(),
+        }
+    }
+
+    /// Render as Graphviz DOT. Documents are boxes, assets ellipses, and missing targets dashed
+    /// red boxes. Edges are labeled with the element and attribute, such as `a[href]`.
+    pub fn dot(&self) -> String {
+        let mut dot = String::from("digraph hyperlink {\n");
+
+        for node in &self.nodes {
+            dot.push_str(&format!(
+                "  {} [{}];\n",
+                dot_string(&node.id),
+                node.kind.dot_attributes()
+            ));
+        }
+
+        for edge in &self.edges {
+            dot.push_str(&format!(
+                "  {} -> {} [label={}];\n",
+                dot_string(&edge.source),
+                dot_string(&edge.target),
+                dot_string(&format!("{}[{}]", edge.element, edge.attribute)),
+            ));
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn graphml(&self) -> String {
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+        for (id, domain, attr_type) in &[
+            ("kind", "node", "string"),
+            ("path", "node", "string"),
+            ("element", "edge", "string"),
+            ("attribute", "edge", "string"),
+            ("count", "edge", "int"),
+        ] {
+            xml.push_str(&format!(
+                "  <key id=\"{0}\" for=\"{1}\" attr.name=\"{0}\" attr.type=\"{2}\"/>\n",
+                id, domain, attr_type
+            ));
+        }
+        xml.push_str("  <graph id=\"hyperlink\" edgedefault=\"directed\">\n");
+
+        for node in &self.nodes {
+            xml.push_str(&format!(
+                "    <node id=\"{}\">\n      <data key=\"kind\">{}</data>\n",
+                xml_escape(&node.id),
+                node.kind.id()
+            ));
+            if let Some(ref path) = node.path {
+                xml.push_str(&format!(
+                    "      <data key=\"path\">{}</data>\n",
+                    xml_escape(path)
+                ));
+            }
+            xml.push_str("    </node>\n");
+        }
+
+        for edge in &self.edges {
+            xml.push_str(&format!(
+                "    <edge source=\"{}\" target=\"{}\">\n",
+                xml_escape(&edge.source),
+                xml_escape(&edge.target),
+            ));
+            xml.push_str(&format!(
+                "      <data key=\"element\">{}</data>\n",
+                edge.element
+            ));
+            xml.push_str(&format!(
+                "      <data key=\"attribute\">{}</data>\n",
+                edge.attribute
+            ));
+            xml.push_str(&format!(
+                "      <data key=\"count\">{}</data>\n",
+                edge.count
+            ));
+            xml.push_str("    </edge>\n");
+        }
+
+        xml.push_str("  </graph>\n</graphml>\n");
+        xml
+    }
+}
+
+/// Quote an ID in DOT.
+fn dot_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[test]
+fn test_render() {
+    let graph = Graph {
+        nodes: vec![
+            Node {
+                id: "/".to_owned(),
+                kind: NodeKind::Document,
+                path: Some("index.html".to_owned()),
+            },
+            Node {
+                id: "/a\"b.html".to_owned(),
+                kind: NodeKind::Missing,
+                path: None,
+            },
+        ],
+        edges: vec![Edge {
+            source: "/".to_owned(),
+            target: "/a\"b.html".to_owned(),
+            element: "a",
+            attribute: "href",
+            count: 2,
+        }],
+    };
+
+    assert_eq!(
+        graph.dot(),
+        r#"digraph hyperlink {
+  "/" [shape=box];
+  "/a\"b.html" [shape=box, style=dashed, color=red];
+  "/" -> "/a\"b.html" [label="a[href]"];
+}
+"#
+    );
+
+    assert_eq!(
+        graph.graphml(),
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="kind" for="node" attr.name="kind" attr.type="string"/>
+  <key id="path" for="node" attr.name="path" attr.type="string"/>
+  <key id="element" for="edge" attr.name="element" attr.type="string"/>
+  <key id="attribute" for="edge" attr.name="attribute" attr.type="string"/>
+  <key id="count" for="edge" attr.name="count" attr.type="int"/>
+  <graph id="hyperlink" edgedefault="directed">
+    <node id="/">
+      <data key="kind">document</data>
+      <data key="path">index.html</data>
+    </node>
+    <node id="/a&quot;b.html">
+      <data key="kind">missing</data>
+    </node>
+    <edge source="/" target="/a&quot;b.html">
+      <data key="element">a</data>
+      <data key="attribute">href</data>
+      <data key="count">2</data>
+    </edge>
+  </graph>
+</graphml>
+"#
+    );
+}
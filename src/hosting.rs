@@ -0,0 +1,822 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Error};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::html::{canonicalize_path, strip_html_extension};
+
+/// Parses the configuration file of a hosting provider into rules, and adds them to the config.
+type ConfigParser = fn(&mut HostingConfig, Arc<PathBuf>, &str) -> Result<(), Error>;
+
+/// Known configuration files by filename. Files with those names are picked up from the root of
+/// the site automatically.
+static CONFIG_PARSERS: &[(&str, ConfigParser)] = &[
+    ("_redirects", parse_netlify_redirects),
+    ("vercel.json", parse_vercel_config),
+    ("firebase.json", parse_firebase_config),
+];
+
+/// A rule from the configuration of a static hosting provider, which makes the server respond to
+/// paths that do not correspond to a file.
+#[derive(Debug)]
+pub struct RedirectRule {
+    /// Matches request paths, i.e. hrefs with a leading slash.
+    pattern: Regex,
+    /// The target of the rule as written in the configuration.
+    pub to: String,
+    /// 200 for rewrites, 3xx for redirects.
+    pub status: u16,
+    /// The configuration file and line this rule comes from.
+    pub path: Arc<PathBuf>,
+    pub lineno: usize,
+}
+
+impl RedirectRule {
+    /// Whether the server serves something for the path, as opposed to responding with an error.
+    fn serves(&self, path: &str) -> bool {
+        self.status < 400 && self.pattern.is_match(path)
+    }
+
+    /// Return the canonicalized target of this rule if it points to a file of this site.
+    /// Targets containing placeholders cannot be checked and are skipped.
+    fn internal_target(&self) -> Option<String> {
+        if !self.to.starts_with('/') || self.to.contains(&[':', '*', '$'][..]) {
+            return None;
+        }
+
+        let path_end = self
+            .to
+            .find(&['?', '#'][..])
+            .unwrap_or_else(|| self.to.len());
+        Some(canonicalize_path(&self.to[..path_end]))
+    }
+
+    /// Where this rule points to within the site, including targets with placeholders.
+    fn target(&self) -> Option<RuleTarget> {
+        let path = self.to.strip_prefix('/')?;
+        let path = &path[..path.find(&['?', '#'][..]).unwrap_or_else(|| path.len())];
+
+        match path.find(&[':', '*', '$'][..]) {
+            Some(placeholder_start) => {
+                Some(RuleTarget::Prefix(path[..placeholder_start].to_owned()))
+            }
+            None => Some(RuleTarget::Href(canonicalize_path(path))),
+        }
+    }
+}
+
+/// Where a rule points to within the site, see `HostingConfig::rule_targets`.
+#[derive(Debug, Eq, PartialEq)]
+pub enum RuleTarget {
+    /// The canonicalized href of a single file, such as `docs/install.html`.
+    Href(String),
+    /// The literal start of a target with placeholders, such as `news/` for `/news/:splat`. The
+    /// rule may point to any file whose href starts with it.
+    Prefix(String),
+}
+
+/// How the server treats trailing slashes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrailingSlash {
+    /// Paths are served with and without trailing slash.
+    Ignore,
+    /// Directories are only served with a trailing slash, files only without.
+    Always,
+    /// Nothing is served with a trailing slash.
+    Never,
+}
+
+impl Default for TrailingSlash {
+    fn default() -> Self {
+        TrailingSlash::Ignore
+    }
+}
+
+impl FromStr for TrailingSlash {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "ignore" => Ok(TrailingSlash::Ignore),
+            "always" => Ok(TrailingSlash::Always),
+            "never" => Ok(TrailingSlash::Never),
+            _ => Err(anyhow!(
+                "Invalid trailing slash policy {}, expected one of: always, never, ignore",
+                s
+            )),
+        }
+    }
+}
+
+impl TrailingSlash {
+    /// Interpret the `trailingSlash` setting of Vercel and Firebase.
+    fn from_config(trailing_slash: bool) -> Self {
+        if trailing_slash {
+            TrailingSlash::Always
+        } else {
+            TrailingSlash::Never
+        }
+    }
+}
+
+/// Redirects and rewrites configured for the hosting provider of the site.
+#[derive(Debug, Default)]
+pub struct HostingConfig {
+    rules: Vec<RedirectRule>,
+    /// Whether `/foo` is served by `foo.html`.
+    pub clean_urls: bool,
+    /// Whether links should use clean URLs, i.e. not link to `foo.html`.
+    pub enforce_clean_urls: bool,
+    pub trailing_slash: TrailingSlash,
+}
+
+impl HostingConfig {
+    /// Read all known configuration files from the root of the site, and all explicitly given
+    /// configuration files.
+    pub fn load(base_path: &Path, config_paths: &[PathBuf]) -> Result<Self, Error> {
+        let mut config = HostingConfig::default();
+
+        for (filename, parser) in CONFIG_PARSERS {
+            let path = base_path.join(filename);
+            match fs::read_to_string(&path) {
+                Ok(contents) => config.add_file(*parser, path, &contents)?,
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        for path in config_paths {
+            let filename = path.file_name().and_then(|x| x.to_str()).unwrap_or("");
+            let parser = match CONFIG_PARSERS.iter().find(|(name, _)| *name == filename) {
+                Some((_, parser)) => *parser,
+                None => {
+                    return Err(anyhow!(
+                        "Unknown hosting configuration file {}, expected one of: {}",
+                        path.display(),
+                        CONFIG_PARSERS
+                            .iter()
+                            .map(|(name, _)| *name)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ))
+                }
+            };
+
+            let contents = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            config.add_file(parser, path.clone(), &contents)?;
+        }
+
+        Ok(config)
+    }
+
+    fn add_file(
+        &mut self,
+        parser: ConfigParser,
+        path: PathBuf,
+        contents: &str,
+    ) -> Result<(), Error> {
+        let display_path = path.display().to_string();
+        parser(self, Arc::new(path), contents)
+            .with_context(|| format!("Failed to parse {}", display_path))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Whether the server serves something for an href that does not correspond to a file.
+    pub fn serves(&self, href: &str) -> bool {
+        let path = format!("/{}", href.trim_end_matches('/'));
+        self.rules.iter().any(|rule| rule.serves(&path))
+    }
+
+    /// With clean URLs enabled, return the href under which the server serves a link to an
+    /// `.html` file.
+    pub fn clean_url(&self, href: &str) -> Option<String> {
+        if !self.clean_urls {
+            return None;
+        }
+
+        let path_end = href.find('#').unwrap_or_else(|| href.len());
+        let (path, anchor) = href.split_at(path_end);
+        Some(format!("{}{}", strip_html_extension(path)?, anchor))
+    }
+
+    /// Return the targets of all rules that point to files of this site. Those files are requested
+    /// without being linked to.
+    pub fn rule_targets(&self) -> impl Iterator<Item = RuleTarget> + '_ {
+        self.rules.iter().filter_map(RedirectRule::target)
+    }
+
+    /// Return all rules whose internal target neither exists nor is served by another rule.
+    pub fn get_broken_rules<'a>(
+        &'a self,
+        is_defined: impl Fn(&str) -> bool + 'a,
+    ) -> impl Iterator<Item = &'a RedirectRule> + 'a {
+        self.rules
+            .iter()
+            .filter(move |rule| match rule.internal_target() {
+                Some(target) => {
+                    !is_defined(&target)
+                        && !self.serves(&target)
+                        && !matches!(self.clean_url(&target), Some(target)
+                            if is_defined(&target))
+                }
+                None => false,
+            })
+    }
+}
+
+/// Parse a `_redirects` file as used by Netlify and Cloudflare Pages.
+///
+/// https://docs.netlify.com/routing/redirects/
+fn parse_netlify_redirects(
+    config: &mut HostingConfig,
+    path: Arc<PathBuf>,
+    contents: &str,
+) -> Result<(), Error> {
+    for (i, line) in contents.lines().enumerate() {
+        let lineno = i + 1;
+        let mut tokens = line.split_whitespace();
+
+        let from = match tokens.next() {
+            Some(x) if !x.starts_with('#') => x,
+            _ => continue,
+        };
+
+        // Query parameters (`id=:id`) may appear between source and target.
+        let to = match tokens.find(|token| !token.contains('=') || token.contains('/')) {
+            Some(x) => x,
+            None => return Err(anyhow!("Missing redirect target on line {}", lineno)),
+        };
+
+        // Status codes may be suffixed with ! to force the rule. Anything after the status code
+        // is a condition such as `Country=de`, which we ignore.
+        let status = match tokens.next() {
+            Some(token) if token.starts_with(|c: char| c.is_ascii_digit()) => token
+                .trim_end_matches('!')
+                .parse()
+                .with_context(|| format!("Invalid status code on line {}", lineno))?,
+            _ => 301,
+        };
+
+        // Rules for other domains do not apply to the paths we check.
+        if !from.starts_with('/') {
+            continue;
+        }
+
+        config.rules.push(RedirectRule {
+            pattern: netlify_pattern(from)?,
+            to: to.to_owned(),
+            status,
+            path: path.clone(),
+            lineno,
+        });
+    }
+
+    Ok(())
+}
+
+/// Compile the source path of a Netlify rule. `*` matches anything (including nothing) and
+/// placeholders such as `:slug` match a single path segment.
+fn netlify_pattern(from: &str) -> Result<Regex, Error> {
+    let mut regex = String::from("^");
+
+    let segments = from
+        .trim_matches('/')
+        .split('/')
+        .filter(|segment| !segment.is_empty());
+
+    for segment in segments {
+        if segment == "*" {
+            regex.push_str("(/.*)?");
+        } else if segment.starts_with(':') {
+            regex.push_str("/[^/]+");
+        } else if let Some(prefix) = segment.strip_suffix('*') {
+            regex.push('/');
+            regex.push_str(&regex::escape(prefix));
+            regex.push_str(".*");
+        } else {
+            regex.push('/');
+            regex.push_str(&regex::escape(segment));
+        }
+    }
+
+    if regex.len() == 1 {
+        regex.push('/');
+    }
+
+    regex.push('$');
+    Ok(Regex::new(&regex)?)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VercelConfig {
+    #[serde(default)]
+    redirects: Vec<VercelRule>,
+    #[serde(default)]
+    rewrites: Vec<VercelRule>,
+    #[serde(default)]
+    clean_urls: bool,
+    trailing_slash: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VercelRule {
+    source: String,
+    destination: String,
+    permanent: Option<bool>,
+    status_code: Option<u16>,
+}
+
+/// Parse the `redirects`, `rewrites`, `cleanUrls` and `trailingSlash` settings of a
+/// `vercel.json`.
+///
+/// https://vercel.com/docs/configuration
+fn parse_vercel_config(
+    config: &mut HostingConfig,
+    path: Arc<PathBuf>,
+    contents: &str,
+) -> Result<(), Error> {
+    let vercel_config: VercelConfig = serde_json::from_str(contents)?;
+    config.clean_urls |= vercel_config.clean_urls;
+    if let Some(trailing_slash) = vercel_config.trailing_slash {
+        config.trailing_slash = TrailingSlash::from_config(trailing_slash);
+    }
+
+    let mut redirect_lines = RuleLines::new(contents, "redirects");
+    let mut rewrite_lines = RuleLines::new(contents, "rewrites");
+
+    let redirects = vercel_config.redirects.into_iter().map(|rule| {
+        let status = match (rule.status_code, rule.permanent) {
+            (Some(status), _) => status,
+            (None, Some(false)) => 307,
+            (None, _) => 308,
+        };
+        let lineno = redirect_lines.lineno(&rule.source);
+        (rule, status, lineno)
+    });
+    let rewrites = vercel_config.rewrites.into_iter().map(|rule| {
+        let lineno = rewrite_lines.lineno(&rule.source);
+        (rule, 200, lineno)
+    });
+
+    for (rule, status, lineno) in redirects.chain(rewrites) {
+        config.rules.push(RedirectRule {
+            pattern: path_to_regexp_pattern(&rule.source)?,
+            lineno,
+            to: rule.destination,
+            status,
+            path: path.clone(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Compile a route pattern as understood by the `path-to-regexp` npm package, which is used by
+/// Vercel and for captures in Firebase. Examples: `/blog/:slug`, `/docs/:path*`, `/(.*)`,
+/// `/post/:id(\d+)`.
+fn path_to_regexp_pattern(source: &str) -> Result<Regex, Error> {
+    let mut regex = String::from("^");
+    let mut chars = source.trim_end_matches('/').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ':' => {
+                while matches!(chars.peek(), Some(c) if c.is_ascii_alphanumeric() || *c == '_') {
+                    chars.next();
+                }
+
+                let param = if chars.peek() == Some(&'(') {
+                    chars.next();
+                    read_group(&mut chars)?
+                } else {
+                    "[^/]+".to_owned()
+                };
+
+                // The slash before a parameter is optional together with the parameter.
+                let has_slash = regex.ends_with('/');
+                if has_slash {
+                    regex.pop();
+                }
+                let slash = if has_slash { "/" } else { "" };
+
+                match chars.peek() {
+                    Some('*') => {
+                        chars.next();
+                        regex.push_str(&format!("(?:{}(?:{})(?:/(?:{}))*)?", slash, param, param));
+                    }
+                    Some('+') => {
+                        chars.next();
+                        regex.push_str(&format!("{}(?:{})(?:/(?:{}))*", slash, param, param));
+                    }
+                    Some('?') => {
+                        chars.next();
+                        regex.push_str(&format!("(?:{}(?:{}))?", slash, param));
+                    }
+                    _ => regex.push_str(&format!("{}(?:{})", slash, param)),
+                }
+            }
+            '(' => {
+                let group = read_group(&mut chars)?;
+                regex.push_str(&format!("(?:{})", group));
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+
+    if regex.len() == 1 {
+        regex.push('/');
+    }
+
+    regex.push('$');
+    Ok(Regex::new(&regex)?)
+}
+
+/// Read the contents of a parenthesized regex group, after the opening parenthesis.
+fn read_group(chars: &mut impl Iterator<Item = char>) -> Result<String, Error> {
+    let mut group = String::new();
+    let mut depth = 1;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                group.push(c);
+                group.extend(chars.next());
+                continue;
+            }
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(group);
+                }
+            }
+            _ => {}
+        }
+
+        group.push(c);
+    }
+
+    Err(anyhow!("Unbalanced parenthesis in pattern"))
+}
+
+#[derive(Deserialize)]
+struct FirebaseConfig {
+    hosting: FirebaseHostingConfigs,
+}
+
+/// Firebase allows configuring multiple sites at once.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FirebaseHostingConfigs {
+    One(FirebaseHostingConfig),
+    Many(Vec<FirebaseHostingConfig>),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FirebaseHostingConfig {
+    #[serde(default)]
+    redirects: Vec<FirebaseRule>,
+    #[serde(default)]
+    rewrites: Vec<FirebaseRule>,
+    #[serde(default)]
+    clean_urls: bool,
+    trailing_slash: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct FirebaseRule {
+    source: Option<String>,
+    glob: Option<String>,
+    regex: Option<String>,
+    /// Rewrites to cloud functions and the like do not have a destination.
+    destination: Option<String>,
+    #[serde(rename = "type")]
+    status: Option<u16>,
+}
+
+impl FirebaseRule {
+    /// The pattern of the rule as written, whichever kind it is.
+    fn source_text(&self) -> &str {
+        self.source
+            .as_ref()
+            .or_else(|| self.glob.as_ref())
+            .or_else(|| self.regex.as_ref())
+            .map_or("", |x| x.as_str())
+    }
+}
+
+/// Parse the `redirects`, `rewrites`, `cleanUrls` and `trailingSlash` settings of a
+/// `firebase.json`.
+///
+/// https://firebase.google.com/docs/hosting/full-config
+fn parse_firebase_config(
+    config: &mut HostingConfig,
+    path: Arc<PathBuf>,
+    contents: &str,
+) -> Result<(), Error> {
+    let firebase_config: FirebaseConfig = serde_json::from_str(contents)?;
+    let hosting_configs = match firebase_config.hosting {
+        FirebaseHostingConfigs::One(x) => vec![x],
+        FirebaseHostingConfigs::Many(x) => x,
+    };
+
+    let mut redirect_lines = RuleLines::new(contents, "redirects");
+    let mut rewrite_lines = RuleLines::new(contents, "rewrites");
+
+    for hosting_config in hosting_configs {
+        config.clean_urls |= hosting_config.clean_urls;
+        if let Some(trailing_slash) = hosting_config.trailing_slash {
+            config.trailing_slash = TrailingSlash::from_config(trailing_slash);
+        }
+
+        let redirects = hosting_config.redirects.into_iter().map(|rule| {
+            let lineno = redirect_lines.lineno(rule.source_text());
+            (rule.status.unwrap_or(301), rule, lineno)
+        });
+        let rewrites = hosting_config.rewrites.into_iter().map(|rule| {
+            let lineno = rewrite_lines.lineno(rule.source_text());
+            (200, rule, lineno)
+        });
+
+        for (status, rule, lineno) in redirects.chain(rewrites) {
+            let pattern = match (rule.source.as_ref().or(rule.glob.as_ref()), rule.regex) {
+                (Some(glob), _) => firebase_glob_pattern(glob)?,
+                (None, Some(regex)) => Regex::new(&format!("^(?:{})$", regex))?,
+                (None, None) => return Err(anyhow!("Rule without source")),
+            };
+
+            let to = rule.destination.unwrap_or_default();
+            config.rules.push(RedirectRule {
+                pattern,
+                lineno,
+                to,
+                status,
+                path: path.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Compile a glob as used by Firebase. `**` matches any path, `*` matches within a path segment,
+/// `{a,b}` matches one of the alternatives, and `:name` captures a path segment.
+fn firebase_glob_pattern(glob: &str) -> Result<Regex, Error> {
+    let mut regex = String::from("^");
+    let mut chars = glob.trim_end_matches('/').chars().peekable();
+    let mut in_braces = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `/**` also matches the directory itself
+                if regex.ends_with('/') {
+                    regex.pop();
+                    regex.push_str("(?:/.*)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '{' if !in_braces => {
+                in_braces = true;
+                regex.push_str("(?:");
+            }
+            '}' if in_braces => {
+                in_braces = false;
+                regex.push(')');
+            }
+            ',' if in_braces => regex.push('|'),
+            ':' => {
+                while matches!(chars.peek(), Some(c) if c.is_ascii_alphanumeric() || *c == '_') {
+                    chars.next();
+                }
+                if chars.peek() == Some(&'*') {
+                    chars.next();
+                    regex.push_str(".*");
+                } else {
+                    regex.push_str("[^/]+");
+                }
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+
+    if regex.len() == 1 {
+        regex.push('/');
+    }
+
+    regex.push('$');
+    Ok(Regex::new(&regex)?)
+}
+
+/// JSON parsers do not keep track of line numbers. Finds the lines of the rules in a list such
+/// as `redirects` for error messages, by searching for their sources in order, starting at the
+/// key of the list.
+struct RuleLines<'a> {
+    contents: &'a str,
+    offset: usize,
+}
+
+impl<'a> RuleLines<'a> {
+    fn new(contents: &'a str, key: &str) -> Self {
+        let offset = contents.find(&format!("\"{}\"", key)).unwrap_or(0);
+        RuleLines { contents, offset }
+    }
+
+    /// Return the line of the next rule with the given source. If the source is written
+    /// differently, e.g. with other escapes, the line of the previous rule is returned.
+    fn lineno(&mut self, source: &str) -> usize {
+        let needle = serde_json::to_string(source).unwrap_or_default();
+        if let Some(i) = self.contents[self.offset..].find(&needle) {
+            self.offset += i + needle.len();
+        }
+
+        self.contents[..self.offset].matches('\n').count() + 1
+    }
+}
+
+#[cfg(test)]
+fn parse_config(parser: ConfigParser, filename: &str, contents: &str) -> HostingConfig {
+    let mut config = HostingConfig::default();
+    parser(&mut config, Arc::new(PathBuf::from(filename)), contents).unwrap();
+    config
+}
+
+#[test]
+fn test_netlify_redirects() {
+    let config = parse_config(
+        parse_netlify_redirects,
+        "_redirects",
+        r#"
+# comment
+/home              /              301
+/blog/*            /news/:splat   301!
+/store id=:id      /blog/:id      301
+/posts/:year/:slug /blog/:slug
+/api/*             https://api.example.com/:splat  200
+/en/*              /en/404.html   404
+https://old.example.com/* https://example.com/:splat 301!
+/                  /de            302  Country=de
+"#,
+    );
+
+    assert_eq!(config.rules.len(), 7);
+    assert_eq!(config.rules[0].lineno, 3);
+    assert_eq!(config.rules[1].status, 301);
+    assert_eq!(config.rules[2].to, "/blog/:id");
+    assert_eq!(config.rules[4].status, 200);
+
+    assert!(config.serves("home"));
+    assert!(!config.serves("homepage"));
+    assert!(config.serves("blog"));
+    assert!(config.serves("blog/2020/hello.html"));
+    assert!(config.serves("store"));
+    assert!(config.serves("posts/2020/hello"));
+    assert!(!config.serves("posts/2020"));
+    assert!(!config.serves("posts/2020/hello/world"));
+    assert!(config.serves("api/v1/users"));
+    assert!(!config.serves("en/missing"));
+    assert!(config.serves(""));
+
+    let targets: Vec<_> = config.rule_targets().collect();
+    assert_eq!(
+        targets,
+        &[
+            RuleTarget::Href("".to_owned()),
+            RuleTarget::Prefix("news/".to_owned()),
+            RuleTarget::Prefix("blog/".to_owned()),
+            RuleTarget::Prefix("blog/".to_owned()),
+            RuleTarget::Href("en/404.html".to_owned()),
+            RuleTarget::Href("de".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn test_vercel_config() {
+    let config = parse_config(
+        parse_vercel_config,
+        "vercel.json",
+        r#"{
+  "cleanUrls": true,
+  "trailingSlash": false,
+  "redirects": [
+    { "source": "/old-blog/:slug", "destination": "/blog/:slug" },
+    { "source": "/docs/:path*", "destination": "/documentation/:path*", "permanent": false },
+    { "source": "/user/:id(\\d+)", "destination": "/users", "statusCode": 301 }
+  ],
+  "rewrites": [
+    { "source": "/app/(.*)", "destination": "/app.html" },
+    { "source": "/:lang(en|de)?/about", "destination": "/about.html" }
+  ]
+}"#,
+    );
+
+    assert!(config.clean_urls);
+    assert_eq!(config.trailing_slash, TrailingSlash::Never);
+    assert_eq!(
+        config.clean_url("blog/post.html#intro").as_deref(),
+        Some("blog/post#intro")
+    );
+    assert_eq!(config.clean_url("blog/post"), None);
+    assert_eq!(config.rules.len(), 5);
+    assert_eq!(config.rules[0].status, 308);
+    assert_eq!(config.rules[1].status, 307);
+    assert_eq!(config.rules[2].status, 301);
+    assert_eq!(config.rules[3].status, 200);
+    assert_eq!(config.rules[3].lineno, 10);
+
+    assert!(config.serves("old-blog/hello"));
+    assert!(!config.serves("old-blog/hello/world"));
+    assert!(config.serves("docs"));
+    assert!(config.serves("docs/a/b/c"));
+    assert!(config.serves("user/123"));
+    assert!(!config.serves("user/abc"));
+    assert!(config.serves("app/settings"));
+    assert!(config.serves("about"));
+    assert!(config.serves("de/about"));
+    assert!(!config.serves("fr/about"));
+}
+
+#[test]
+fn test_firebase_config() {
+    let config = parse_config(
+        parse_firebase_config,
+        "firebase.json",
+        r#"{
+  "hosting": {
+    "public": "public",
+    "cleanUrls": true,
+    "redirects": [
+      { "source": "/foo", "destination": "/bar", "type": 301 },
+      { "source": "/blog/:post*", "destination": "/news/:post", "type": 302 },
+      { "source": "/old/**", "destination": "/bar", "type": 301 },
+      { "regex": "/users/\\d+", "destination": "/users", "type": 301 }
+    ],
+    "rewrites": [
+      { "source": "/{api,functions}/*", "function": "api" },
+      { "source": "/docs/**/*.md", "destination": "/docs.html" },
+      { "glob": "/app/**", "destination": "/app.html" }
+    ]
+  }
+}"#,
+    );
+
+    assert!(config.clean_urls);
+    assert_eq!(config.rules.len(), 7);
+    assert_eq!(config.rules[4].to, "");
+    assert_eq!(config.rules[2].lineno, 8);
+    assert_eq!(config.rules[3].lineno, 9);
+    assert_eq!(config.rules[4].lineno, 12);
+    assert_eq!(config.rules[6].lineno, 14);
+
+    assert!(config.serves("foo"));
+    assert!(config.serves("blog/a/b"));
+    assert!(config.serves("old"));
+    assert!(config.serves("old/a/b"));
+    assert!(!config.serves("older"));
+    assert!(config.serves("users/123"));
+    assert!(config.serves("api/users"));
+    assert!(config.serves("functions/users"));
+    assert!(!config.serves("api/users/123"));
+    assert!(config.serves("docs/a/b.md"));
+    assert!(!config.serves("docs/a/b.html"));
+    assert!(config.serves("app/settings"));
+}
+
+#[test]
+fn test_broken_rules() {
+    let config = parse_config(
+        parse_netlify_redirects,
+        "_redirects",
+        r#"
+/a        /exists/index.html
+/b        /missing/
+/c        /a
+/d        /missing/:splat
+/e        https://example.com
+"#,
+    );
+
+    let broken: Vec<_> = config
+        .get_broken_rules(|href| href == "exists")
+        .map(|rule| rule.lineno)
+        .collect();
+    assert_eq!(broken, &[3]);
+}
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::fmt;
 use std::fs;
 use std::io::{BufReader, Read};
@@ -11,12 +12,18 @@ use bumpalo::collections::String as BumpString;
 use quick_xml::events::Event;
 use quick_xml::Reader;
 
+use crate::hosting::TrailingSlash;
+use crate::ignore::{IgnoreComment, IgnoreRules};
 use crate::paragraph::ParagraphWalker;
+use crate::site::SiteUrl;
 
 static BAD_SCHEMAS: &[&str] = &[
     "http://", "https://", "irc://", "ftp://", "mailto:", "data:",
 ];
 
+/// Schemas of links that can be checked with `--check-external`.
+static EXTERNAL_SCHEMAS: &[&str] = &["http://", "https://"];
+
 static PARAGRAPH_TAGS: &[&[u8]] = &[b"p", b"li", b"dt", b"dd"];
 
 #[inline]
@@ -51,6 +58,36 @@ fn push_and_canonicalize(base: &mut BumpString, path: &str) {
     }
 }
 
+/// Strip the `.html` or `.htm` extension of the last path component, as servers with clean URLs
+/// do. Returns `None` if there is no such extension.
+pub fn strip_html_extension(path: &str) -> Option<&str> {
+    let stripped = path
+        .strip_suffix(".html")
+        .or_else(|| path.strip_suffix(".htm"))?;
+
+    if stripped.is_empty() || stripped.ends_with('/') {
+        return None;
+    }
+
+    Some(stripped)
+}
+
+/// See `SiteUrl::localize`.
+fn localize<'a>(site_url: Option<&SiteUrl>, href: &'a str) -> Option<Cow<'a, str>> {
+    match site_url {
+        Some(site_url) => site_url.localize(href),
+        None => Some(Cow::Borrowed(href)),
+    }
+}
+
+/// Canonicalize a path relative to the root of the site, in the same way hrefs of links are.
+pub fn canonicalize_path(path: &str) -> String {
+    let arena = bumpalo::Bump::new();
+    let mut href = BumpString::new_in(&arena);
+    push_and_canonicalize(&mut href, path);
+    href.as_str().to_owned()
+}
+
 #[cfg(test)]
 mod test_push_and_canonicalize {
     use super::push_and_canonicalize as push_and_canonicalize_impl;
@@ -116,6 +153,53 @@ mod test_push_and_canonicalize {
     }
 }
 
+/// Iterate over the URLs of a `srcset` attribute, discarding width and density descriptors.
+///
+/// https://html.spec.whatwg.org/multipage/images.html#parsing-a-srcset-attribute
+fn parse_srcset(value: &str) -> impl Iterator<Item = &str> {
+    let mut rest = value;
+
+    std::iter::from_fn(move || {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+        if rest.is_empty() {
+            return None;
+        }
+
+        let url_end = rest
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or_else(|| rest.len());
+        let url = &rest[..url_end];
+        rest = &rest[url_end..];
+
+        if url.ends_with(',') {
+            // No descriptors, the comma terminates the candidate.
+            return Some(url.trim_end_matches(','));
+        }
+
+        // Skip over descriptors such as `2x` or `640w`.
+        rest = &rest[rest.find(',').unwrap_or_else(|| rest.len())..];
+        Some(url)
+    })
+}
+
+#[test]
+fn test_parse_srcset() {
+    let urls: Vec<_> = parse_srcset("a.png").collect();
+    assert_eq!(urls, &["a.png"]);
+
+    let urls: Vec<_> = parse_srcset("a.png 1x, b.png 2x").collect();
+    assert_eq!(urls, &["a.png", "b.png"]);
+
+    let urls: Vec<_> = parse_srcset("  a.png   640w,\n\tb.png 1280w  ,c.png").collect();
+    assert_eq!(urls, &["a.png", "b.png", "c.png"]);
+
+    let urls: Vec<_> = parse_srcset("a.png, b.png 2x,,").collect();
+    assert_eq!(urls, &["a.png", "b.png"]);
+
+    let urls: Vec<_> = parse_srcset("").collect();
+    assert!(urls.is_empty());
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub struct Href<'a>(pub &'a str);
 
@@ -137,11 +221,28 @@ impl<'a> fmt::Display for Href<'a> {
     }
 }
 
+/// The element and attribute a link was found in, such as `a[href]` or `img[srcset]`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub struct LinkTag {
+    pub element: &'static str,
+    pub attribute: &'static str,
+}
+
+impl fmt::Display for LinkTag {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}[{}]", self.element, self.attribute)
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub struct UsedLink<'a, P> {
     pub href: Href<'a>,
     pub path: Arc<PathBuf>,
     pub paragraph: Option<P>,
+    pub tag: LinkTag,
+    /// Whether problems with this link are not reported, because an ignore rule matches its raw
+    /// href.
+    pub ignored: bool,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
@@ -152,22 +253,47 @@ pub struct DefinedLink<'a> {
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub enum Link<'a, P> {
     Uses(UsedLink<'a, P>),
+    /// A http(s) link to another server. The href is the absolute URL without its fragment.
+    UsesExternal(UsedLink<'a, P>),
     Defines(DefinedLink<'a>),
 }
 
 impl<'a, P> Link<'a, P> {
     pub fn into_paragraph(self) -> Option<P> {
         match self {
-            Link::Uses(UsedLink { paragraph, .. }) => paragraph,
+            Link::Uses(UsedLink { paragraph, .. })
+            | Link::UsesExternal(UsedLink { paragraph, .. }) => paragraph,
             Link::Defines(_) => None,
         }
     }
 }
 
+/// Controls which links are extracted from a document.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LinkOptions<'a> {
+    /// Whether to extract anchor definitions, and retain anchors on used links.
+    pub check_anchors: bool,
+    /// Whether to extract http(s) links as `Link::UsesExternal`.
+    pub check_external: bool,
+    /// Whether to determine the paragraph each used link is in.
+    pub get_paragraphs: bool,
+    /// Unless ignored, trailing slashes of used links are retained such that they can be checked
+    /// against the policy.
+    pub trailing_slash: TrailingSlash,
+    /// Where the site is deployed, such that absolute links into the site can be checked.
+    pub site_url: Option<&'a SiteUrl>,
+    /// Ignore rules to match raw hrefs against, i.e. hrefs as written in the document.
+    pub ignore_rules: Option<&'a IgnoreRules>,
+}
+
+#[derive(Clone)]
 pub struct Document {
     pub path: Arc<PathBuf>,
     href: String,
     pub is_index_html: bool,
+    /// Whether the document is served as a directory, such that relative links in it are resolved
+    /// within that directory.
+    is_directory: bool,
 }
 
 impl Document {
@@ -203,7 +329,33 @@ impl Document {
             path: Arc::new(path.to_owned()),
             href,
             is_index_html,
+            is_directory: is_index_html,
+        }
+    }
+
+    /// Strip the `.html` extension from the href of this document, as it is served with clean
+    /// URLs.
+    pub fn with_clean_url(mut self) -> Self {
+        if let Some(stripped) = strip_html_extension(&self.href) {
+            self.href.truncate(stripped.len());
         }
+        self
+    }
+
+    /// Append a trailing slash to the href of an index document, as the server only serves
+    /// directories with a trailing slash.
+    pub fn with_trailing_slash(mut self) -> Self {
+        if self.is_index_html && !self.href.is_empty() {
+            self.href.push('/');
+        }
+        self
+    }
+
+    /// Serve an index document without trailing slash, as the server never serves directories
+    /// with one. Browsers then resolve relative links in it against its parent directory.
+    pub fn without_trailing_slash(mut self) -> Self {
+        self.is_directory = false;
+        self
     }
 
     pub fn href(&self) -> Href<'_> {
@@ -215,19 +367,49 @@ impl Document {
         arena: &'b bumpalo::Bump,
         preserve_anchor: bool,
         rel_href: &str,
+    ) -> Href<'b> {
+        self.join_with_base(arena, None, preserve_anchor, false, rel_href)
+    }
+
+    /// Like `join`, but resolve `rel_href` against `base` instead of the document's own href if
+    /// given. `base` is expected to be the output of `resolve_base`.
+    ///
+    /// With `preserve_trailing_slash`, a trailing slash of `rel_href` is retained in the result.
+    fn join_with_base<'b>(
+        &self,
+        arena: &'b bumpalo::Bump,
+        base: Option<&str>,
+        preserve_anchor: bool,
+        preserve_trailing_slash: bool,
+        rel_href: &str,
     ) -> Href<'b> {
         let qs_start = rel_href
             .find(&['?', '#'][..])
             .unwrap_or_else(|| rel_href.len());
         let anchor_start = rel_href.find('#').unwrap_or_else(|| rel_href.len());
 
-        let mut href = BumpString::from_str_in(&self.href, arena);
-        if self.is_index_html {
-            href.push('/');
+        let mut href = match base {
+            Some(base) => BumpString::from_str_in(base, arena),
+            None => {
+                let mut href = BumpString::from_str_in(&self.href, arena);
+                if self.is_directory && !href.ends_with('/') {
+                    href.push('/');
+                }
+                href
+            }
+        };
+
+        let rel_path = &rel_href[..qs_start];
+        if preserve_trailing_slash && rel_path.is_empty() {
+            // A link to the document (or base) itself, in the form it is defined as.
+            href = BumpString::from_str_in(base.unwrap_or(&self.href), arena);
+        } else {
+            push_and_canonicalize(&mut href, rel_path);
+            if preserve_trailing_slash && rel_path.ends_with('/') && !href.is_empty() {
+                href.push('/');
+            }
         }
 
-        push_and_canonicalize(&mut href, &rel_href[..qs_start]);
-
         if preserve_anchor {
             let anchor = &rel_href[anchor_start..];
             if anchor.len() > 1 {
@@ -238,25 +420,40 @@ impl Document {
         Href(href.into_bump_str())
     }
 
+    /// Resolve the value of a `<base href>` element against the document. Returns `None` if the
+    /// base would not change anything.
+    ///
+    /// A base pointing to a directory retains its trailing slash, such that relative links are
+    /// resolved inside of that directory.
+    fn resolve_base<'b>(&self, arena: &'b bumpalo::Bump, base_href: &str) -> Option<&'b str> {
+        let path_end = base_href
+            .find(&['?', '#'][..])
+            .unwrap_or_else(|| base_href.len());
+        let path = &base_href[..path_end];
+
+        if path.is_empty() {
+            return None;
+        }
+
+        let mut base = BumpString::from_str_in(self.join(arena, false, path).0, arena);
+        if path.ends_with('/') && !base.is_empty() {
+            base.push('/');
+        }
+
+        Some(base.into_bump_str())
+    }
+
     pub fn links<'b, 'l, P: ParagraphWalker>(
         &self,
         arena: &'b bumpalo::Bump,
         xml_buf: &mut Vec<u8>,
         sink: &mut BumpVec<'b, Link<'l, P::Paragraph>>,
-        check_anchors: bool,
-        get_paragraphs: bool,
+        options: &LinkOptions,
     ) -> Result<(), Error>
     where
         'b: 'l,
     {
-        self.links_from_read::<_, P>(
-            arena,
-            xml_buf,
-            sink,
-            fs::File::open(&*self.path)?,
-            check_anchors,
-            get_paragraphs,
-        )
+        self.links_from_read::<_, P>(arena, xml_buf, sink, fs::File::open(&*self.path)?, options)
     }
 
     fn links_from_read<'b, 'l, R: Read, P: ParagraphWalker>(
@@ -265,12 +462,20 @@ impl Document {
         xml_buf: &mut Vec<u8>,
         sink: &mut BumpVec<'b, Link<'l, P::Paragraph>>,
         read: R,
-        check_anchors: bool,
-        get_paragraphs: bool,
+        options: &LinkOptions,
     ) -> Result<(), Error>
     where
         'b: 'l,
     {
+        let LinkOptions {
+            check_anchors,
+            check_external,
+            get_paragraphs,
+            trailing_slash,
+            site_url,
+            ignore_rules,
+        } = *options;
+
         let mut reader = Reader::from_reader(BufReader::new(read));
         reader.trim_text(true);
         reader.expand_empty_elements(true);
@@ -281,36 +486,130 @@ impl Document {
         let mut last_paragraph_i = sink.len();
         let mut in_paragraph = false;
 
+        // Set by the first <base href> element. Only links *after* that element are resolved
+        // against it.
+        let mut base: Option<&'b str> = None;
+        let mut seen_base = false;
+        // A base pointing to another server makes all relative links external.
+        let mut has_external_base = false;
+
+        // Set by comments, see `IgnoreComment`.
+        let mut ignore_next = false;
+        let mut in_ignore_region = false;
+
         loop {
             match reader.read_event(xml_buf)? {
                 Event::Eof => break,
+                Event::Comment(ref e) => {
+                    // Comments in other encodings cannot be ignore comments, and are skipped.
+                    let text = str::from_utf8(e.escaped()).ok();
+                    match text.and_then(|text| IgnoreComment::from_text(text.trim())) {
+                        Some(IgnoreComment::Next) => ignore_next = true,
+                        Some(IgnoreComment::Start) => in_ignore_region = true,
+                        Some(IgnoreComment::End) => in_ignore_region = false,
+                        None => {}
+                    }
+                }
                 Event::Start(ref e) => {
+                    let links_start = sink.len();
+
                     if PARAGRAPH_TAGS.contains(&e.name()) {
                         in_paragraph = true;
                         last_paragraph_i = sink.len();
                         paragraph_walker.finish_paragraph();
                     }
 
-                    macro_rules! extract_used_link {
-                        ($attr_name:expr) => {
-                            for attr in e.html_attributes().with_checks(false) {
-                                let attr = attr?;
-
-                                if attr.key == $attr_name
-                                    && BAD_SCHEMAS
+                    macro_rules! push_used_link {
+                        ($value:expr, $tag:expr) => {
+                            let value: &str = $value;
+                            let ignored = matches!(
+                                ignore_rules,
+                                Some(rules) if rules.ignores_raw_href(value)
+                            );
+
+                            match localize(site_url, value) {
+                                _ if has_external_base => {
+                                    // cannot resolve relative links against other servers
+                                }
+                                None => {
+                                    // absolute path outside of the site
+                                }
+                                Some(ref value)
+                                    if check_external
+                                        && EXTERNAL_SCHEMAS
+                                            .iter()
+                                            .any(|schema| value.starts_with(schema)) =>
+                                {
+                                    let url_end = value.find('#').unwrap_or_else(|| value.len());
+                                    sink.push(Link::UsesExternal(UsedLink {
+                                        href: Href(
+                                            BumpString::from_str_in(&value[..url_end], arena)
+                                                .into_bump_str(),
+                                        ),
+                                        path: self.path.clone(),
+                                        paragraph: None,
+                                        tag: $tag,
+                                        ignored,
+                                    }));
+                                }
+                                Some(ref value)
+                                    if BAD_SCHEMAS
                                         .iter()
-                                        .all(|schema| !attr.value.starts_with(schema.as_bytes()))
+                                        .all(|schema| !value.starts_with(schema)) =>
                                 {
                                     sink.push(Link::Uses(UsedLink {
-                                        href: self.join(
+                                        href: self.join_with_base(
                                             arena,
+                                            base,
                                             check_anchors,
-                                            str::from_utf8(&attr.value)?,
+                                            trailing_slash != TrailingSlash::Ignore,
+                                            value,
                                         ),
                                         path: self.path.clone(),
                                         paragraph: None,
+                                        tag: $tag,
+                                        ignored,
                                     }));
                                 }
+                                Some(_) => {}
+                            }
+                        };
+                    }
+
+                    macro_rules! extract_used_link {
+                        ($element:expr, $attr_name:expr) => {
+                            for attr in e.html_attributes().with_checks(false) {
+                                let attr = attr?;
+
+                                if attr.key == $attr_name.as_bytes() {
+                                    push_used_link!(
+                                        str::from_utf8(&attr.value)?,
+                                        LinkTag {
+                                            element: $element,
+                                            attribute: $attr_name,
+                                        }
+                                    );
+                                }
+                            }
+                        };
+                    }
+
+                    macro_rules! extract_used_links_from_srcset {
+                        ($element:expr, $attr_name:expr) => {
+                            for attr in e.html_attributes().with_checks(false) {
+                                let attr = attr?;
+
+                                if attr.key == $attr_name.as_bytes() {
+                                    for url in parse_srcset(str::from_utf8(&attr.value)?) {
+                                        push_used_link!(
+                                            url,
+                                            LinkTag {
+                                                element: $element,
+                                                attribute: $attr_name,
+                                            }
+                                        );
+                                    }
+                                }
                             }
                         };
                     }
@@ -326,8 +625,16 @@ impl Document {
                                         href.push('#');
                                         href.push_str(str::from_utf8(&attr.value)?);
 
+                                        // Anchors are defined on the href the document is
+                                        // served under, including its trailing slash.
                                         sink.push(Link::Defines(DefinedLink {
-                                            href: self.join(arena, check_anchors, &href),
+                                            href: self.join_with_base(
+                                                arena,
+                                                None,
+                                                check_anchors,
+                                                trailing_slash != TrailingSlash::Ignore,
+                                                &href,
+                                            ),
                                         }));
                                     }
                                 }
@@ -336,20 +643,63 @@ impl Document {
                     }
 
                     match e.name() {
+                        b"base" if !seen_base => {
+                            for attr in e.html_attributes().with_checks(false) {
+                                let attr = attr?;
+
+                                if attr.key == b"href" {
+                                    let value = localize(site_url, str::from_utf8(&attr.value)?);
+                                    seen_base = true;
+
+                                    match value {
+                                        Some(ref value)
+                                            if BAD_SCHEMAS
+                                                .iter()
+                                                .all(|schema| !value.starts_with(schema))
+                                                && !value.starts_with("//") =>
+                                        {
+                                            base = self.resolve_base(arena, value);
+                                        }
+                                        _ => has_external_base = true,
+                                    }
+                                }
+                            }
+                        }
                         b"a" => {
-                            extract_used_link!(b"href");
+                            extract_used_link!("a", "href");
                             extract_anchor_def!(b"name");
                         }
-                        b"img" => extract_used_link!(b"src"),
-                        b"link" => extract_used_link!(b"href"),
-                        b"script" => extract_used_link!(b"src"),
-                        b"iframe" => extract_used_link!(b"src"),
-                        b"area" => extract_used_link!(b"href"),
-                        b"object" => extract_used_link!(b"data"),
+                        b"img" => {
+                            extract_used_link!("img", "src");
+                            extract_used_links_from_srcset!("img", "srcset");
+                        }
+                        b"source" => extract_used_links_from_srcset!("source", "srcset"),
+                        b"link" => extract_used_link!("link", "href"),
+                        b"script" => extract_used_link!("script", "src"),
+                        b"iframe" => extract_used_link!("iframe", "src"),
+                        b"area" => extract_used_link!("area", "href"),
+                        b"object" => extract_used_link!("object", "data"),
                         _ => {}
                     }
 
                     extract_anchor_def!(b"id");
+
+                    let ignored = in_ignore_region
+                        || ignore_next
+                        || e.html_attributes()
+                            .with_checks(false)
+                            .any(|attr| matches!(attr, Ok(ref attr) if attr.key == b"data-hyperlink-ignore"));
+
+                    for link in &mut sink[links_start..] {
+                        match link {
+                            Link::Uses(ref mut x) | Link::UsesExternal(ref mut x) => {
+                                x.ignored |= ignored;
+                                // The comment only applies to the first element with links
+                                ignore_next = false;
+                            }
+                            Link::Defines(_) => {}
+                        }
+                    }
                 }
                 Event::End(e) if get_paragraphs => {
                     if PARAGRAPH_TAGS.contains(&e.name()) {
@@ -357,7 +707,7 @@ impl Document {
                         if in_paragraph {
                             for link in &mut sink[last_paragraph_i..] {
                                 match link {
-                                    Link::Uses(ref mut x) => {
+                                    Link::Uses(ref mut x) | Link::UsesExternal(ref mut x) => {
                                         x.paragraph = paragraph.clone();
                                     }
                                     Link::Defines(_) => {}
@@ -398,6 +748,9 @@ fn test_document_href() {
         doc.href(),
         Href("platforms/python/troubleshooting.html".into())
     );
+
+    let doc = doc.with_clean_url();
+    assert_eq!(doc.href(), Href("platforms/python/troubleshooting".into()));
 }
 
 #[test]
@@ -427,8 +780,7 @@ fn test_document_links() {
     <a href='../../go/?foo=bar&bar=baz' href='../../go/'>
     """#
         .as_bytes(),
-        false,
-        false,
+        &LinkOptions::default(),
     )
     .unwrap();
 
@@ -437,6 +789,11 @@ fn test_document_links() {
             href: Href(x.into()),
             path: doc.path.clone(),
             paragraph: None,
+            tag: LinkTag {
+                element: "a",
+                attribute: "href",
+            },
+            ignored: false,
         })
     };
 
@@ -452,6 +809,417 @@ fn test_document_links() {
     );
 }
 
+#[test]
+fn test_document_links_srcset() {
+    use crate::paragraph::ParagraphHasher;
+
+    let arena = bumpalo::Bump::new();
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/blog/index.html"));
+
+    let mut links = BumpVec::new_in(&arena);
+
+    doc.links_from_read::<_, ParagraphHasher>(
+        &arena,
+        &mut Vec::new(),
+        &mut links,
+        r#"""
+    <img src="small.png" srcset="small.png 1x, large.png 2x">
+    <picture>
+        <source srcset="/img/photo.webp 640w, /img/photo@2x.webp 1280w" type="image/webp">
+        <source srcset="https://example.com/photo.avif">
+        <img src="/img/photo.jpg">
+    </picture>
+    """#
+        .as_bytes(),
+        &LinkOptions::default(),
+    )
+    .unwrap();
+
+    let used_link = |x: &'static str, element, attribute| {
+        Link::Uses(UsedLink {
+            href: Href(x.into()),
+            path: doc.path.clone(),
+            paragraph: None,
+            tag: LinkTag { element, attribute },
+            ignored: false,
+        })
+    };
+
+    assert_eq!(
+        &links,
+        &[
+            used_link("blog/small.png", "img", "src"),
+            used_link("blog/small.png", "img", "srcset"),
+            used_link("blog/large.png", "img", "srcset"),
+            used_link("img/photo.webp", "source", "srcset"),
+            used_link("img/photo@2x.webp", "source", "srcset"),
+            used_link("img/photo.jpg", "img", "src"),
+        ]
+    );
+}
+
+#[test]
+fn test_document_links_base() {
+    use crate::paragraph::ParagraphHasher;
+
+    let arena = bumpalo::Bump::new();
+
+    let doc = Document::new(
+        Path::new("public/"),
+        Path::new("public/reference/client.html"),
+    );
+
+    let mut links = BumpVec::new_in(&arena);
+
+    doc.links_from_read::<_, ParagraphHasher>(
+        &arena,
+        &mut Vec::new(),
+        &mut links,
+        r##"""
+    <head>
+    <link rel="stylesheet" href="style.css">
+    <base href="/api/v2/">
+    <base href="/ignored/">
+    </head>
+    <a href="client.html">
+    <a href="../v1/">
+    <a href="/install">
+    <a href="#methods">
+    <a id="methods">
+    """##
+            .as_bytes(),
+        &LinkOptions {
+            check_anchors: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let used_link = |x: &'static str, element, attribute| {
+        Link::Uses(UsedLink {
+            href: Href(x.into()),
+            path: doc.path.clone(),
+            paragraph: None,
+            tag: LinkTag { element, attribute },
+            ignored: false,
+        })
+    };
+
+    assert_eq!(
+        &links,
+        &[
+            used_link("reference/style.css", "link", "href"),
+            used_link("api/v2/client.html", "a", "href"),
+            used_link("api/v1", "a", "href"),
+            used_link("install", "a", "href"),
+            used_link("api/v2#methods", "a", "href"),
+            Link::Defines(DefinedLink {
+                href: Href("reference/client.html#methods".into())
+            }),
+        ]
+    );
+}
+
+#[test]
+fn test_document_links_trailing_slash() {
+    use crate::paragraph::ParagraphHasher;
+
+    let arena = bumpalo::Bump::new();
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/docs/index.html"))
+        .with_trailing_slash();
+    assert_eq!(doc.href(), Href("docs/"));
+
+    let mut links = BumpVec::new_in(&arena);
+
+    doc.links_from_read::<_, ParagraphHasher>(
+        &arena,
+        &mut Vec::new(),
+        &mut links,
+        r##"""
+    <a href="install/">
+    <a href="install">
+    <a href="../">
+    <a href="">
+    <a href="#usage">
+    <a href="api/?version=2">
+    """##
+            .as_bytes(),
+        &LinkOptions {
+            trailing_slash: TrailingSlash::Always,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let used_link = |x: &'static str| {
+        Link::Uses(UsedLink {
+            href: Href(x.into()),
+            path: doc.path.clone(),
+            paragraph: None,
+            tag: LinkTag {
+                element: "a",
+                attribute: "href",
+            },
+            ignored: false,
+        })
+    };
+
+    assert_eq!(
+        &links,
+        &[
+            used_link("docs/install/"),
+            used_link("docs/install"),
+            used_link(""),
+            used_link("docs/"),
+            used_link("docs/"),
+            used_link("docs/api/"),
+        ]
+    );
+}
+
+#[test]
+fn test_document_links_no_trailing_slash() {
+    use crate::paragraph::ParagraphHasher;
+
+    let arena = bumpalo::Bump::new();
+
+    // Served as /docs, so relative links resolve against the root.
+    let doc = Document::new(Path::new("public/"), Path::new("public/docs/index.html"))
+        .without_trailing_slash();
+    assert_eq!(doc.href(), Href("docs"));
+
+    let mut links = BumpVec::new_in(&arena);
+
+    doc.links_from_read::<_, ParagraphHasher>(
+        &arena,
+        &mut Vec::new(),
+        &mut links,
+        r##"""
+    <a href="install/">
+    <a href="./">
+    <a href="">
+    <a href="../about.html">
+    """##
+            .as_bytes(),
+        &LinkOptions {
+            trailing_slash: TrailingSlash::Never,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let used_link = |x: &'static str| {
+        Link::Uses(UsedLink {
+            href: Href(x),
+            path: doc.path.clone(),
+            paragraph: None,
+            tag: LinkTag {
+                element: "a",
+                attribute: "href",
+            },
+            ignored: false,
+        })
+    };
+
+    assert_eq!(
+        &links,
+        &[
+            used_link("install/"),
+            used_link(""),
+            used_link("docs"),
+            used_link("about.html"),
+        ]
+    );
+}
+
+#[test]
+fn test_document_links_ignored() {
+    use crate::paragraph::ParagraphHasher;
+
+    let arena = bumpalo::Bump::new();
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+
+    let mut links = BumpVec::new_in(&arena);
+
+    doc.links_from_read::<_, ParagraphHasher>(
+        &arena,
+        &mut Vec::new(),
+        &mut links,
+        r#"""
+    <a href="a.html">
+    <!-- hyperlink-ignore-next -->
+    <p>
+    <a href="b.html">
+    <a href="c.html">
+    <a href="d.html" data-hyperlink-ignore>
+    <!-- hyperlink-ignore-start -->
+    <img src="e.png">
+    <a href="f.html">
+    <!-- hyperlink-ignore-end -->
+    <a href="g.html">
+    """#
+        .as_bytes(),
+        &LinkOptions::default(),
+    )
+    .unwrap();
+
+    let used_link = |x: &'static str, element, ignored| {
+        Link::Uses(UsedLink {
+            href: Href(x.into()),
+            path: doc.path.clone(),
+            paragraph: None,
+            tag: LinkTag {
+                element,
+                attribute: if element == "img" { "src" } else { "href" },
+            },
+            ignored,
+        })
+    };
+
+    assert_eq!(
+        &links,
+        &[
+            used_link("a.html", "a", false),
+            used_link("b.html", "a", true),
+            used_link("c.html", "a", false),
+            used_link("d.html", "a", true),
+            used_link("e.png", "img", true),
+            used_link("f.html", "a", true),
+            used_link("g.html", "a", false),
+        ]
+    );
+}
+
+#[test]
+fn test_document_links_latin1_comment() {
+    use crate::paragraph::ParagraphHasher;
+
+    let arena = bumpalo::Bump::new();
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+
+    let mut links = BumpVec::new_in(&arena);
+
+    doc.links_from_read::<_, ParagraphHasher>(
+        &arena,
+        &mut Vec::new(),
+        &mut links,
+        &b"<!-- \xa9 2003 --><a href=\"a.html\">"[..],
+        &LinkOptions::default(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        &links,
+        &[Link::Uses(UsedLink {
+            href: Href("a.html"),
+            path: doc.path.clone(),
+            paragraph: None,
+            tag: LinkTag {
+                element: "a",
+                attribute: "href",
+            },
+            ignored: false,
+        })]
+    );
+}
+
+#[test]
+fn test_document_links_external_base() {
+    use crate::paragraph::ParagraphHasher;
+
+    let arena = bumpalo::Bump::new();
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+
+    let mut links = BumpVec::new_in(&arena);
+
+    doc.links_from_read::<_, ParagraphHasher>(
+        &arena,
+        &mut Vec::new(),
+        &mut links,
+        r#"""
+    <link rel="stylesheet" href="style.css">
+    <base href="https://example.com/">
+    <a href="foo.html">
+    """#
+        .as_bytes(),
+        &LinkOptions::default(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        &links,
+        &[Link::Uses(UsedLink {
+            href: Href("style.css".into()),
+            path: doc.path.clone(),
+            paragraph: None,
+            tag: LinkTag {
+                element: "link",
+                attribute: "href",
+            },
+            ignored: false,
+        })]
+    );
+}
+
+#[test]
+fn test_document_links_external() {
+    use crate::paragraph::ParagraphHasher;
+
+    let arena = bumpalo::Bump::new();
+
+    let doc = Document::new(Path::new("public/"), Path::new("public/index.html"));
+
+    let mut links = BumpVec::new_in(&arena);
+
+    doc.links_from_read::<_, ParagraphHasher>(
+        &arena,
+        &mut Vec::new(),
+        &mut links,
+        r#"""
+    <a href="https://example.com/foo#bar">
+    <a href="mailto:foo@example.com">
+    <a href="bar.html">
+    """#
+        .as_bytes(),
+        &LinkOptions {
+            check_external: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        &links,
+        &[
+            Link::UsesExternal(UsedLink {
+                href: Href("https://example.com/foo".into()),
+                path: doc.path.clone(),
+                paragraph: None,
+                tag: LinkTag {
+                    element: "a",
+                    attribute: "href",
+                },
+                ignored: false,
+            }),
+            Link::Uses(UsedLink {
+                href: Href("bar.html".into()),
+                path: doc.path.clone(),
+                paragraph: None,
+                tag: LinkTag {
+                    element: "a",
+                    attribute: "href",
+                },
+                ignored: false,
+            }),
+        ]
+    );
+}
+
 #[test]
 fn test_document_join_index_html() {
     let arena = bumpalo::Bump::new();
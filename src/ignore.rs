@@ -0,0 +1,178 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Error};
+use globset::{GlobBuilder, GlobMatcher};
+use regex::Regex;
+
+/// A glob, or a regular expression if prefixed with `regex:`.
+#[derive(Debug)]
+pub enum Pattern {
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Self, Error> {
+        if let Some(regex) = pattern.strip_prefix("regex:") {
+            let regex = Regex::new(regex)
+                .with_context(|| format!("Invalid regular expression {}", regex))?;
+            Ok(Pattern::Regex(regex))
+        } else {
+            // `*` should not match across path segments, as in .gitignore
+            let glob = GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("Invalid glob {}", pattern))?;
+            Ok(Pattern::Glob(glob.compile_matcher()))
+        }
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        match self {
+            Pattern::Glob(glob) => glob.is_match(text),
+            Pattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+/// Rules for problems that should not be reported, e.g. because they are in generated pages that
+/// cannot be fixed.
+#[derive(Debug, Default)]
+pub struct IgnoreRules {
+    /// Relative to the static file path, such that document paths can be matched.
+    base_path: PathBuf,
+    hrefs: Vec<Pattern>,
+    raw_hrefs: Vec<Pattern>,
+    paths: Vec<Pattern>,
+}
+
+impl IgnoreRules {
+    pub fn new(base_path: PathBuf) -> Self {
+        IgnoreRules {
+            base_path,
+            ..Default::default()
+        }
+    }
+
+    /// Ignore links by their resolved href, e.g. `blog/2014/**`.
+    pub fn add_href(&mut self, pattern: &str) -> Result<(), Error> {
+        self.hrefs.push(Pattern::new(pattern)?);
+        Ok(())
+    }
+
+    /// Ignore links by their href as written in the HTML, e.g. `../changelog/*`.
+    pub fn add_raw_href(&mut self, pattern: &str) -> Result<(), Error> {
+        self.raw_hrefs.push(Pattern::new(pattern)?);
+        Ok(())
+    }
+
+    /// Ignore all links in documents matching the path relative to the static file path, e.g.
+    /// `vendor/**/changelog.html`.
+    pub fn add_path(&mut self, pattern: &str) -> Result<(), Error> {
+        self.paths.push(Pattern::new(pattern)?);
+        Ok(())
+    }
+
+    pub fn has_raw_hrefs(&self) -> bool {
+        !self.raw_hrefs.is_empty()
+    }
+
+    pub fn ignores_raw_href(&self, raw_href: &str) -> bool {
+        self.raw_hrefs
+            .iter()
+            .any(|pattern| pattern.is_match(raw_href))
+    }
+
+    /// Whether a problem with the resolved href found in the file should not be reported.
+    pub fn ignores(&self, href: &str, path: &Path) -> bool {
+        if self.hrefs.iter().any(|pattern| pattern.is_match(href)) {
+            return true;
+        }
+
+        if self.paths.is_empty() {
+            return false;
+        }
+
+        let path = site_path(&self.base_path, path);
+        self.paths.iter().any(|pattern| pattern.is_match(&path))
+    }
+}
+
+/// The path of a file relative to the static file path, with forward slashes. Files outside of
+/// the static file path, such as hosting configuration, are returned as-is.
+pub fn site_path(base_path: &Path, path: &Path) -> String {
+    let path = path.strip_prefix(base_path).unwrap_or(path);
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// Comments in HTML or markdown that suppress problems with links.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IgnoreComment {
+    Next,
+    Start,
+    End,
+}
+
+impl IgnoreComment {
+    /// Parse a line consisting of an HTML comment such as `<!-- hyperlink-ignore-next -->`.
+    pub fn parse(line: &str) -> Option<Self> {
+        let text = line
+            .trim()
+            .strip_prefix("<!--")?
+            .strip_suffix("-->")?
+            .trim();
+        IgnoreComment::from_text(text)
+    }
+
+    /// Parse the text of a comment: `hyperlink-ignore-next` suppresses problems in the next
+    /// paragraph (markdown) or element with links (HTML). `hyperlink-ignore-start` and
+    /// `hyperlink-ignore-end` suppress all problems in between.
+    pub fn from_text(text: &str) -> Option<Self> {
+        match text {
+            "hyperlink-ignore-next" => Some(IgnoreComment::Next),
+            "hyperlink-ignore-start" => Some(IgnoreComment::Start),
+            "hyperlink-ignore-end" => Some(IgnoreComment::End),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_ignore_rules() {
+    let mut rules = IgnoreRules::new(PathBuf::from("public"));
+    rules.add_href("blog/*/index.html").unwrap();
+    rules.add_href("regex:^changelog#v[0-9]").unwrap();
+    rules.add_raw_href("../legacy/**").unwrap();
+    rules.add_path("vendor/**").unwrap();
+
+    let ignores = |href, path| rules.ignores(href, Path::new(path));
+
+    assert!(ignores("blog/2014/index.html", "public/index.html"));
+    assert!(!ignores("blog/2014/05/index.html", "public/index.html"));
+    assert!(ignores("changelog#v1.0", "public/index.html"));
+    assert!(!ignores("changelog#latest", "public/index.html"));
+    assert!(ignores("docs/install", "public/vendor/foo/index.html"));
+    assert!(!ignores("docs/install", "public/docs/vendor/index.html"));
+    assert!(!ignores("../legacy/foo.html", "public/index.html"));
+
+    assert!(rules.has_raw_hrefs());
+    assert!(rules.ignores_raw_href("../legacy/foo/bar.html"));
+    assert!(!rules.ignores_raw_href("legacy/foo.html"));
+
+    assert!(rules.add_href("regex:(").is_err());
+    assert!(rules.add_path("{a,b").is_err());
+}
+
+#[test]
+fn test_ignore_comment() {
+    assert_eq!(
+        IgnoreComment::parse("<!-- hyperlink-ignore-next -->"),
+        Some(IgnoreComment::Next)
+    );
+    assert_eq!(
+        IgnoreComment::parse("  <!--hyperlink-ignore-start-->"),
+        Some(IgnoreComment::Start)
+    );
+    assert_eq!(IgnoreComment::parse("<!-- hyperlink-ignore-next"), None);
+    assert_eq!(IgnoreComment::parse("<!-- TODO -->"), None);
+}
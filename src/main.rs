@@ -1,27 +1,101 @@
+mod baseline;
 mod collector;
+mod config;
+mod external;
+mod fix;
+mod graph;
+mod hosting;
 mod html;
+mod ignore;
 mod markdown;
 mod paragraph;
+mod report;
+mod site;
+mod stats;
+mod suggestions;
 
 use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsStr;
 use std::mem;
 use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{anyhow, Context, Error};
+use baseline::Baseline;
 use bumpalo::collections::vec::Vec as BumpVec;
 use jwalk::WalkDir;
 use markdown::DocumentSource;
 use rayon::prelude::*;
 use structopt::StructOpt;
 
-use collector::{BrokenLinkCollector, LinkCollector, UsedLinkCollector};
-use html::{DefinedLink, Document, Link};
+use collector::{
+    find_target, BrokenLinkCollector, BrokenLinkKind, CrawlCollector, GraphCollector,
+    LinkCollector, OwnedUsedLink, StatsCollector, UsedLinkCollector,
+};
+use config::Config;
+use external::{ExternalChecker, ExternalCheckerOptions};
+use fix::Fixes;
+use graph::{Graph, GraphFormat};
+use hosting::{HostingConfig, RuleTarget, TrailingSlash};
+use html::{DefinedLink, Document, Link, LinkOptions};
+use ignore::{site_path, IgnoreRules, Pattern};
 use paragraph::{DebugParagraphWalker, NoopParagraphWalker, ParagraphHasher, ParagraphWalker};
+use report::{Format, Location, Problem, ProblemKind, Reporter};
+use site::SiteUrl;
+use stats::{Stats, StatsFormat};
 
 static MARKDOWN_FILES: &[&str] = &["md", "mdx"];
 static HTML_FILES: &[&str] = &["htm", "html"];
 
+/// Files that are requested without being linked to, and are never reported as orphaned. They are
+/// matched against the path within the static file path, so `index.html` is only the root index.
+static ENTRY_POINTS: &[&str] = &[
+    "index.html",
+    "404.html",
+    "sitemap.xml",
+    "robots.txt",
+    "favicon.ico",
+    "_redirects",
+    "_headers",
+    "vercel.json",
+    "firebase.json",
+    "hyperlink.toml",
+];
+
+/// Extensions of files that are checked for links, and of their markdown sources.
+struct FileTypes {
+    html: Vec<String>,
+    markdown: Vec<String>,
+}
+
+impl Default for FileTypes {
+    fn default() -> Self {
+        FileTypes {
+            html: HTML_FILES.iter().map(|x| (*x).to_owned()).collect(),
+            markdown: MARKDOWN_FILES.iter().map(|x| (*x).to_owned()).collect(),
+        }
+    }
+}
+
+impl FileTypes {
+    fn is_html(&self, path: &Path) -> bool {
+        has_extension(path, &self.html)
+    }
+
+    fn is_markdown(&self, path: &Path) -> bool {
+        has_extension(path, &self.markdown)
+    }
+}
+
+fn has_extension(path: &Path, extensions: &[String]) -> bool {
+    matches!(
+        path.extension().and_then(OsStr::to_str),
+        Some(extension) if extensions.iter().any(|x| x.trim_start_matches('.') == extension)
+    )
+}
+
 #[derive(StructOpt)]
 #[structopt(name = "hyperlink")]
 struct Cli {
@@ -32,22 +106,172 @@ struct Cli {
     #[structopt(verbatim_doc_comment)]
     base_path: Option<PathBuf>,
 
+    /// Configuration file with defaults for the options below. Defaults to hyperlink.toml in the
+    /// static file path or in the current directory.
+    #[structopt(long = "config")]
+    config_path: Option<PathBuf>,
+
     /// How many threads to use, default is to try and saturate CPU.
     #[structopt(short = "j", long = "jobs")]
     threads: Option<usize>,
 
     /// Whether to check for valid anchor references.
-    #[structopt(long = "check-anchors")]
+    #[structopt(long = "check-anchors", overrides_with = "no-check-anchors")]
     check_anchors: bool,
 
+    /// Do not check anchors, even if enabled in the configuration file.
+    #[structopt(long = "no-check-anchors", overrides_with = "check-anchors")]
+    no_check_anchors: bool,
+
+    /// Whether to check external http(s) links by making requests to them.
+    #[structopt(long = "check-external", overrides_with = "no-check-external")]
+    check_external: bool,
+
+    /// Do not check external links, even if enabled in the configuration file.
+    #[structopt(long = "no-check-external", overrides_with = "check-external")]
+    no_check_external: bool,
+
+    /// Whether to report HTML files that cannot be reached by following links from the entry
+    /// points. Assets are not reported, as they may be referenced from CSS.
+    #[structopt(long = "check-orphans", overrides_with = "no-check-orphans")]
+    check_orphans: bool,
+
+    /// Do not report orphaned files, even if enabled in the configuration file.
+    #[structopt(long = "no-check-orphans", overrides_with = "check-orphans")]
+    no_check_orphans: bool,
+
+    /// Only check files that can be reached by following links from the entry points, and report
+    /// all other HTML files as unreachable.
+    #[structopt(long = "crawl", overrides_with = "no-crawl")]
+    crawl: bool,
+
+    /// Check all files, even if crawling is enabled in the configuration file.
+    #[structopt(long = "no-crawl", overrides_with = "crawl")]
+    no_crawl: bool,
+
+    /// Files that --check-orphans and --crawl start from, in addition to index.html, 404.html,
+    /// sitemap.xml and others at the root, and the targets of redirects. Globs are matched against the path within the static
+    /// file path, e.g. `**/index.html` for all index files. Can be given multiple times.
+    #[structopt(long = "entry-point", number_of_values = 1)]
+    entry_points: Vec<String>,
+
+    /// How many requests to make against a single host at the same time.
+    #[structopt(long = "external-concurrency", default_value = "4")]
+    external_concurrency: usize,
+
+    /// How often to retry requests to external links that may have failed temporarily.
+    #[structopt(long = "external-retries", default_value = "2")]
+    external_retries: usize,
+
+    /// Timeout in seconds for each request to an external link.
+    #[structopt(long = "external-timeout", default_value = "10")]
+    external_timeout: u64,
+
+    /// File to cache successfully checked external links in, such that they are not requested
+    /// on every run.
+    #[structopt(long = "external-cache")]
+    external_cache: Option<PathBuf>,
+
+    /// How long to keep entries in the external link cache, in seconds.
+    #[structopt(long = "external-cache-ttl", default_value = "86400")]
+    external_cache_ttl: u64,
+
+    /// Assume that the server serves foo.html under /foo, as most static hosting providers do.
+    #[structopt(long = "clean-urls", overrides_with = "no-clean-urls")]
+    clean_urls: bool,
+
+    /// Do not assume clean URLs, even if enabled in the configuration file. Clean URLs enabled
+    /// in the configuration of the hosting provider still apply.
+    #[structopt(long = "no-clean-urls", overrides_with = "clean-urls")]
+    no_clean_urls: bool,
+
+    /// Report links that use the .html extension although the site is served with clean URLs.
+    /// Implies --clean-urls.
+    #[structopt(long = "enforce-clean-urls", overrides_with = "no-enforce-clean-urls")]
+    enforce_clean_urls: bool,
+
+    /// Do not report links with .html extension, even if enabled in the configuration file.
+    #[structopt(long = "no-enforce-clean-urls", overrides_with = "enforce-clean-urls")]
+    no_enforce_clean_urls: bool,
+
+    /// How the server treats trailing slashes: with "always", directories are only served with
+    /// a trailing slash and files only without. With "never", nothing is served with a trailing
+    /// slash. Links that do not conform are reported. Defaults to the trailingSlash setting in
+    /// vercel.json or firebase.json, or "ignore".
+    #[structopt(long = "trailing-slash")]
+    trailing_slash: Option<TrailingSlash>,
+
+    /// The URL the site is deployed under, such as /docs/ or https://example.com/docs/. Links
+    /// starting with it are checked as links within the static file path. Other absolute paths
+    /// are skipped.
+    #[structopt(long = "base-url")]
+    base_url: Option<String>,
+
+    /// Check absolute http(s) links to this domain as links within the static file path, such as
+    /// docs.example.com. Can be given multiple times.
+    #[structopt(long = "internal-domain", number_of_values = 1)]
+    internal_domains: Vec<String>,
+
+    /// Redirect and rewrite configuration of your hosting provider, if it does not live in the
+    /// static file path. The format is determined by the filename: _redirects (Netlify),
+    /// vercel.json or firebase.json. Can be given multiple times.
+    #[structopt(long = "hosting-config", number_of_values = 1)]
+    hosting_configs: Vec<PathBuf>,
+
+    /// Do not report problems with links whose resolved href matches a glob, such as
+    /// "blog/2014/**", or a regular expression prefixed with "regex:". Can be given multiple
+    /// times.
+    #[structopt(long = "ignore-href", number_of_values = 1)]
+    ignore_hrefs: Vec<String>,
+
+    /// Do not report problems with links whose href as written in the HTML matches a glob or
+    /// regular expression. Can be given multiple times.
+    #[structopt(long = "ignore-raw-href", number_of_values = 1)]
+    ignore_raw_hrefs: Vec<String>,
+
+    /// Do not report problems in files whose path within the static file path matches a glob or
+    /// regular expression, such as "vendor/**". Can be given multiple times.
+    #[structopt(long = "ignore-path", number_of_values = 1)]
+    ignore_paths: Vec<String>,
+
+    /// Do not report problems that are listed in this file, written by --write-baseline. Only new
+    /// problems fail the check, and problems from the baseline that have been fixed are listed.
+    #[structopt(long = "baseline")]
+    baseline_path: Option<PathBuf>,
+
+    /// Write all problems that were found to this file, such that they can be accepted with
+    /// --baseline.
+    #[structopt(long = "write-baseline")]
+    write_baseline_path: Option<PathBuf>,
+
+    /// How many existing hrefs to suggest for each broken link, such as pages with a similar path
+    /// or the same filename in another directory. Defaults to 0, which disables suggestions.
+    #[structopt(long = "suggestions", default_value = "0")]
+    suggestions: usize,
+
     /// Path to directory of markdown files to use for reporting errors.
     #[structopt(long = "sources")]
     sources_path: Option<PathBuf>,
 
+    /// Rewrite broken links in the markdown files of --sources, if there is exactly one existing
+    /// href they may have been meant to point to and it is clear where they were written.
+    #[structopt(long = "fix")]
+    fix: bool,
+
+    /// With --fix, print the changes as unified diff after the report instead of writing them.
+    #[structopt(long = "dry-run", requires = "fix")]
+    dry_run: bool,
+
     /// Enable specialized output for GitHub actions.
     #[structopt(long = "github-actions")]
     github_actions: bool,
 
+    /// Output format: "text" (default), "json", "ndjson" for one JSON object per line, "sarif",
+    /// "junit" (JUnit XML) or "gitlab" (GitLab Code Quality). Progress messages are printed to
+    /// stderr for all formats but text.
+    #[structopt(long = "format")]
+    format: Option<Format>,
+
     /// Utilities for development of hyperlink.
     #[structopt(subcommand)]
     subcommand: Option<Subcommand>,
@@ -83,15 +307,78 @@ enum Subcommand {
         base_path: PathBuf,
         sources_path: PathBuf,
     },
+
+    /// Export all files of the site and the links between them as a graph, to visualize the
+    /// navigation structure.
+    ///
+    /// Nodes are documents, assets, anchors and targets of broken links. Edges are labeled with the
+    /// element and attribute the link was found in, such as a[href] or img[src].
+    Graph {
+        base_path: PathBuf,
+
+        /// Output format: "dot" (Graphviz, default), "graphml" or "json".
+        #[structopt(long = "format", default_value = "dot")]
+        format: GraphFormat,
+
+        /// Include anchors as nodes, and link to them instead of their documents.
+        #[structopt(long = "check-anchors")]
+        check_anchors: bool,
+    },
+
+    /// Print statistics about the site: how many documents link to each document and how many
+    /// files each document links to, how many clicks are needed to reach it from the index page,
+    /// and how many assets of each type there are.
+    Stats {
+        base_path: PathBuf,
+
+        /// Output format: "text" (default) or "json".
+        #[structopt(long = "format", default_value = "text")]
+        format: StatsFormat,
+
+        /// How many documents to list as most linked.
+        #[structopt(long = "top", default_value = "10")]
+        top: usize,
+    },
 }
 
 fn main() -> Result<(), Error> {
     let Cli {
         base_path,
+        config_path,
         threads,
         check_anchors,
+        no_check_anchors,
+        check_external,
+        no_check_external,
+        check_orphans,
+        no_check_orphans,
+        crawl,
+        no_crawl,
+        entry_points,
+        external_concurrency,
+        external_retries,
+        external_timeout,
+        external_cache,
+        external_cache_ttl,
+        clean_urls,
+        no_clean_urls,
+        enforce_clean_urls,
+        no_enforce_clean_urls,
+        trailing_slash,
+        base_url,
+        internal_domains,
+        hosting_configs,
+        ignore_hrefs,
+        ignore_raw_hrefs,
+        ignore_paths,
+        baseline_path,
+        write_baseline_path,
+        suggestions,
         sources_path,
+        fix,
+        dry_run,
         github_actions,
+        format,
         subcommand,
     } = Cli::from_args();
 
@@ -102,7 +389,9 @@ fn main() -> Result<(), Error> {
             .unwrap();
     }
 
-    match subcommand {
+    // The graph and stats subcommands resolve links like the check does, so they are only run
+    // once the configuration has been loaded.
+    let base_path = match subcommand {
         Some(Subcommand::DumpParagraphs { file }) => {
             return dump_paragraphs(file);
         }
@@ -112,10 +401,28 @@ fn main() -> Result<(), Error> {
         }) => {
             return match_all_paragraphs(base_path, sources_path);
         }
-        None => {}
-    }
+        Some(Subcommand::Graph { ref base_path, .. })
+        | Some(Subcommand::Stats { ref base_path, .. }) => Some(base_path.clone()),
+        None => base_path,
+    };
 
-    let base_path = match base_path {
+    let config = match config_path {
+        Some(ref config_path) => Config::load(config_path)?,
+        None => {
+            let mut config = None;
+            if let Some(ref base_path) = base_path {
+                config = Config::discover(base_path)?;
+            }
+            if config.is_none() {
+                // An empty path refers to the current directory, without adding ./ to paths
+                // from the configuration file.
+                config = Config::discover(Path::new(""))?;
+            }
+            config.unwrap_or_default()
+        }
+    };
+
+    let base_path = match base_path.or(config.base_path) {
         Some(base_path) => base_path,
         None => {
             // Invalid invocation. Ultra hack to show help if no arguments are provided. Structopt
@@ -129,171 +436,489 @@ fn main() -> Result<(), Error> {
         }
     };
 
-    if sources_path.is_some() {
-        check_links::<ParagraphHasher>(base_path, check_anchors, sources_path, github_actions)
+    let check_anchors = merge_flag(check_anchors, no_check_anchors, config.check_anchors);
+    let check_orphans = merge_flag(check_orphans, no_check_orphans, config.check_orphans);
+    let crawl = merge_flag(crawl, no_crawl, config.crawl);
+    let sources_path = sources_path.or(config.sources);
+    if fix && sources_path.is_none() {
+        return Err(anyhow!("--fix requires --sources"));
+    }
+    let clean_urls = merge_flag(clean_urls, no_clean_urls, config.clean_urls);
+    let enforce_clean_urls = merge_flag(
+        enforce_clean_urls,
+        no_enforce_clean_urls,
+        config.enforce_clean_urls,
+    );
+    let trailing_slash = trailing_slash.or(config.trailing_slash);
+    let base_url = base_url.or(config.base_url);
+    let internal_domains = if internal_domains.is_empty() {
+        config.internal_domains
+    } else {
+        internal_domains
+    };
+    let hosting_configs = if hosting_configs.is_empty() {
+        config.hosting_configs
+    } else {
+        hosting_configs
+    };
+    let mut file_types = FileTypes::default();
+    if let Some(html_extensions) = config.html_extensions {
+        file_types.html = html_extensions;
+    }
+    if let Some(markdown_extensions) = config.markdown_extensions {
+        file_types.markdown = markdown_extensions;
+    }
+
+    let external_checker = if merge_flag(check_external, no_check_external, config.check_external) {
+        Some(ExternalChecker::new(ExternalCheckerOptions {
+            concurrency_per_host: external_concurrency,
+            retries: external_retries,
+            timeout: Duration::from_secs(external_timeout),
+            cache_path: external_cache.or(config.external_cache),
+            cache_ttl: Duration::from_secs(external_cache_ttl),
+        }))
+    } else {
+        None
+    };
+
+    let mut hosting = HostingConfig::load(&base_path, &hosting_configs)?;
+    hosting.clean_urls |= clean_urls || enforce_clean_urls;
+    hosting.enforce_clean_urls = enforce_clean_urls;
+    if let Some(trailing_slash) = trailing_slash {
+        hosting.trailing_slash = trailing_slash;
+    }
+
+    let mut site_url = base_url.as_deref().map(SiteUrl::new).transpose()?;
+    for domain in &internal_domains {
+        site_url
+            .get_or_insert_with(SiteUrl::default)
+            .add_internal_domain(domain)?;
+    }
+
+    match subcommand {
+        Some(Subcommand::Graph {
+            format,
+            check_anchors: graph_check_anchors,
+            ..
+        }) => {
+            let link_options = LinkOptions {
+                check_anchors: check_anchors || graph_check_anchors,
+                trailing_slash: hosting.trailing_slash,
+                site_url: site_url.as_ref(),
+                ..Default::default()
+            };
+            return graph(&base_path, format, &file_types, &link_options, &hosting);
+        }
+        Some(Subcommand::Stats { format, top, .. }) => {
+            let link_options = LinkOptions {
+                trailing_slash: hosting.trailing_slash,
+                site_url: site_url.as_ref(),
+                ..Default::default()
+            };
+            return stats(
+                &base_path,
+                format,
+                top,
+                &file_types,
+                &link_options,
+                &hosting,
+            );
+        }
+        _ => {}
+    }
+
+    // Ignore rules from the command line are added to those from the configuration file.
+    let mut ignore_rules = IgnoreRules::new(base_path.clone());
+    for pattern in config.ignore_hrefs.iter().chain(&ignore_hrefs) {
+        ignore_rules.add_href(pattern)?;
+    }
+    for pattern in config.ignore_raw_hrefs.iter().chain(&ignore_raw_hrefs) {
+        ignore_rules.add_raw_href(pattern)?;
+    }
+    for pattern in config.ignore_paths.iter().chain(&ignore_paths) {
+        ignore_rules.add_path(pattern)?;
+    }
+
+    // Entry points from the configuration file and the command line are added to the defaults.
+    let entry_points = ENTRY_POINTS
+        .iter()
+        .copied()
+        .chain(config.entry_points.iter().map(String::as_str))
+        .chain(entry_points.iter().map(String::as_str))
+        .map(Pattern::new)
+        .collect::<Result<_, _>>()?;
+
+    let mut reporter = Reporter::new(
+        format.or(config.format).unwrap_or(Format::Text),
+        github_actions,
+        config.severity,
+    );
+    reporter.roots = sources_path
+        .iter()
+        .chain(Some(&base_path))
+        .cloned()
+        .collect();
+    if let Some(baseline_path) = baseline_path.or(config.baseline) {
+        reporter.baseline = Some(Baseline::load(&baseline_path, base_path.clone())?);
+    }
+    if let Some(write_baseline_path) = write_baseline_path {
+        reporter.new_baseline = Some((write_baseline_path, Baseline::new(base_path.clone())));
+    }
+
+    let options = CheckOptions {
+        base_path,
+        sources_path,
+        file_types,
+        check_anchors,
+        check_orphans,
+        crawl,
+        entry_points,
+        external_checker,
+        hosting,
+        site_url,
+        ignore_rules,
+        suggestions,
+        fix,
+        dry_run,
+    };
+
+    if options.sources_path.is_some() {
+        check_links::<ParagraphHasher>(options, reporter)
     } else {
-        check_links::<NoopParagraphWalker>(base_path, check_anchors, sources_path, github_actions)
+        check_links::<NoopParagraphWalker>(options, reporter)
     }
 }
 
-fn check_links<P: ParagraphWalker>(
+/// Combine a flag from the command line, given as `--foo` or `--no-foo`, with its value from the
+/// configuration file.
+fn merge_flag(enabled: bool, disabled: bool, config: bool) -> bool {
+    if enabled {
+        true
+    } else if disabled {
+        false
+    } else {
+        config
+    }
+}
+
+/// Everything that determines which links are checked, and how.
+struct CheckOptions {
     base_path: PathBuf,
-    check_anchors: bool,
     sources_path: Option<PathBuf>,
-    github_actions: bool,
+    file_types: FileTypes,
+    check_anchors: bool,
+    check_orphans: bool,
+    crawl: bool,
+    /// Files that are not reported as orphaned, and that crawling starts from.
+    entry_points: Vec<Pattern>,
+    external_checker: Option<ExternalChecker>,
+    hosting: HostingConfig,
+    site_url: Option<SiteUrl>,
+    ignore_rules: IgnoreRules,
+    /// How many hrefs to suggest for each broken link.
+    suggestions: usize,
+    /// Whether to rewrite broken links in markdown sources.
+    fix: bool,
+    /// Whether to only print the changes to the markdown sources.
+    dry_run: bool,
+}
+
+/// Add a problem to the report, attributing it to the markdown sources of its paragraph if
+/// possible.
+fn add_problem<P: Ord>(
+    reporter: &mut Reporter,
+    paragraps_to_sourcefile: &MarkdownResult<P>,
+    ignore_rules: &IgnoreRules,
+    kind: ProblemKind,
+    link: OwnedUsedLink<P>,
+    details: Option<String>,
+    suggestions: Vec<String>,
+) -> Result<(), Error> {
+    if link.ignored || ignore_rules.ignores(&link.href, &link.path) {
+        reporter.suppress();
+        return Ok(());
+    }
+
+    let mut sources = Vec::new();
+
+    if let Some(ref paragraph) = link.paragraph {
+        if let Some(document_sources) = paragraps_to_sourcefile.get(paragraph) {
+            debug_assert!(!document_sources.is_empty());
+
+            for (source, lineno, ignored) in document_sources {
+                if !ignored {
+                    sources.push(Location {
+                        path: source.path.clone(),
+                        lineno: Some(*lineno),
+                    });
+                }
+            }
+
+            // Suppressed by comments in all markdown files the link may come from
+            if sources.is_empty() {
+                reporter.suppress();
+                return Ok(());
+            }
+        }
+    }
+
+    reporter.add(Problem {
+        kind,
+        href: link.href,
+        details,
+        path: link.path,
+        lineno: None,
+        sources,
+        suggestions,
+    })
+}
+
+fn check_links<P: ParagraphWalker>(
+    options: CheckOptions,
+    mut reporter: Reporter,
 ) -> Result<(), Error>
 where
     P::Paragraph: Copy + PartialEq,
 {
-    println!("Reading files");
+    let CheckOptions {
+        base_path,
+        sources_path,
+        file_types,
+        check_anchors,
+        check_orphans,
+        crawl,
+        entry_points,
+        external_checker,
+        hosting,
+        site_url,
+        ignore_rules,
+        suggestions,
+        fix,
+        dry_run,
+    } = options;
+
+    reporter.track(ProblemKind::BadLink);
+    if !hosting.is_empty() {
+        reporter.track(ProblemKind::BadRedirect);
+    }
+    if hosting.trailing_slash != TrailingSlash::Ignore {
+        reporter.track(ProblemKind::BadTrailingSlash);
+    }
+    if check_anchors {
+        reporter.track(ProblemKind::BadAnchor);
+    }
+    if hosting.enforce_clean_urls {
+        reporter.track(ProblemKind::HtmlExtension);
+    }
+    if external_checker.is_some() {
+        reporter.track(ProblemKind::BadExternalLink);
+    }
+    if check_orphans {
+        reporter.track(ProblemKind::Orphan);
+    }
+    if crawl {
+        reporter.track(ProblemKind::Unreachable);
+    }
 
-    let html_result = extract_html_links::<BrokenLinkCollector<_>, P>(
-        &base_path,
+    reporter.progress(format_args!("Reading files"));
+
+    let link_options = LinkOptions {
         check_anchors,
-        sources_path.is_some(),
-    )?;
+        check_external: external_checker.is_some(),
+        get_paragraphs: sources_path.is_some(),
+        trailing_slash: hosting.trailing_slash,
+        site_url: site_url.as_ref(),
+        ignore_rules: Some(&ignore_rules).filter(|rules| rules.has_raw_hrefs()),
+    };
+    let mut html_result = if crawl || check_orphans {
+        // Orphaned files are still checked, but unreachable files are not.
+        crawl_html_links::<BrokenLinkCollector<_>, P>(
+            &base_path,
+            &file_types,
+            &entry_points,
+            &link_options,
+            &hosting,
+            !crawl,
+        )?
+    } else {
+        extract_html_links::<BrokenLinkCollector<_>, P>(
+            &base_path,
+            &file_types,
+            &link_options,
+            &hosting,
+        )?
+    };
 
     let paragraps_to_sourcefile = if let Some(ref sources_path) = sources_path {
-        println!("Reading source files");
-        extract_markdown_paragraphs::<P>(sources_path)?
+        reporter.progress(format_args!("Reading source files"));
+        extract_markdown_paragraphs::<P>(sources_path, &file_types)?
     } else {
         BTreeMap::new()
     };
 
-    let used_links_len = html_result.collector.used_links_count();
-    println!(
+    reporter.summary.links = html_result.collector.used_links_count();
+    reporter.summary.files = html_result.files.len();
+    reporter.summary.documents = html_result.documents.len();
+    reporter.documents = mem::take(&mut html_result.documents);
+    reporter.progress(format_args!(
         "Checking {} links from {} files ({} documents)",
-        used_links_len, html_result.file_count, html_result.documents_count,
-    );
+        reporter.summary.links, reporter.summary.files, reporter.summary.documents,
+    ));
+
+    // Fixes need to know whether there is exactly one candidate, no matter how many suggestions
+    // are reported.
+    let mut fixes = Fixes::default();
+    let max_suggestions = if fix {
+        suggestions.max(Fixes::SUGGESTIONS)
+    } else {
+        suggestions
+    };
 
-    let mut bad_links_and_anchors = BTreeMap::new();
-    let mut bad_links_count = 0;
-    let mut bad_anchors_count = 0;
+    for broken_link in
+        html_result
+            .collector
+            .get_broken_links(check_anchors, &hosting, max_suggestions)
+    {
+        let kind = match broken_link.kind {
+            BrokenLinkKind::Missing => ProblemKind::BadLink,
+            BrokenLinkKind::MissingAnchor => ProblemKind::BadAnchor,
+            BrokenLinkKind::HtmlExtension => ProblemKind::HtmlExtension,
+            BrokenLinkKind::TrailingSlash => ProblemKind::BadTrailingSlash,
+        };
 
-    for broken_link in html_result.collector.get_broken_links(check_anchors) {
-        let mut had_sources = false;
+        let mut link_suggestions = broken_link.suggestions;
+        fixes.add_suggestions(&broken_link.link.href, &link_suggestions);
+        link_suggestions.truncate(suggestions);
+
+        add_problem(
+            &mut reporter,
+            &paragraps_to_sourcefile,
+            &ignore_rules,
+            kind,
+            broken_link.link,
+            None,
+            link_suggestions,
+        )?;
+    }
 
-        if broken_link.hard_404 {
-            bad_links_count += 1;
-        } else {
-            bad_anchors_count += 1;
-        }
+    // Index documents may be defined with a trailing slash, see Document::with_trailing_slash.
+    let is_defined = |href: &str| {
+        html_result.collector.is_defined(href)
+            || html_result.collector.is_defined(&format!("{}/", href))
+    };
 
-        if let Some(ref paragraph) = broken_link.link.paragraph {
-            if let Some(document_sources) = &paragraps_to_sourcefile.get(paragraph) {
-                debug_assert!(!document_sources.is_empty());
-                had_sources = true;
+    for rule in hosting.get_broken_rules(is_defined) {
+        if ignore_rules.ignores(&rule.to, &rule.path) {
+            reporter.suppress();
+            continue;
+        }
 
-                for (source, lineno) in *document_sources {
-                    let (bad_links, bad_anchors) = bad_links_and_anchors
-                        .entry((!had_sources, source.path.clone()))
-                        .or_insert_with(|| (BTreeSet::new(), BTreeSet::new()));
+        reporter.add(Problem {
+            kind: ProblemKind::BadRedirect,
+            href: rule.to.clone(),
+            details: None,
+            path: rule.path.clone(),
+            lineno: Some(rule.lineno),
+            sources: Vec::new(),
+            suggestions: Vec::new(),
+        })?;
+    }
 
-                    if broken_link.hard_404 {
-                        bad_links
-                    } else {
-                        bad_anchors
-                    }
-                    .insert((Some(*lineno), broken_link.link.href.clone()));
-                }
-            }
+    let unreached_kind = if crawl {
+        ProblemKind::Unreachable
+    } else {
+        ProblemKind::Orphan
+    };
+    for document in &html_result.unreached {
+        // Assets may be referenced from CSS, which is not read.
+        if !file_types.is_html(&document.path) {
+            continue;
         }
 
-        if !had_sources {
-            let (bad_links, bad_anchors) = bad_links_and_anchors
-                .entry((!had_sources, broken_link.link.path))
-                .or_insert_with(|| (BTreeSet::new(), BTreeSet::new()));
-
-            if broken_link.hard_404 {
-                bad_links
-            } else {
-                bad_anchors
-            }
-            .insert((None, broken_link.link.href));
+        let href = document.href().0.to_owned();
+        if ignore_rules.ignores(&href, &document.path) {
+            reporter.suppress();
+            continue;
         }
+
+        reporter.add(Problem {
+            kind: unreached_kind,
+            href,
+            details: None,
+            path: document.path.clone(),
+            lineno: None,
+            sources: Vec::new(),
+            suggestions: Vec::new(),
+        })?;
     }
 
-    // _is_raw_file is an unused parameter that is only there to control iteration order over keys.
-    // Sort markdown files to the start since otherwise the less valuable annotations on not
-    // checked in files fill up the limit on annotations (tested manually, seems to be 10 right
-    // now).
-    for ((_is_raw_file, filepath), (bad_links, bad_anchors)) in bad_links_and_anchors {
-        println!("{}", filepath.display());
+    if let Some(ref external_checker) = external_checker {
+        let external_links_count = html_result.collector.external_urls().count();
+        reporter.summary.external_links = Some(external_links_count);
+        reporter.progress(format_args!(
+            "Checking {} external links",
+            external_links_count
+        ));
 
-        for (lineno, href) in &bad_links {
-            print_href_error("error: bad link", &href, *lineno);
-        }
+        for (url, status) in external_checker.check(html_result.collector.external_urls())? {
+            if status.is_ok() {
+                continue;
+            }
 
-        for (lineno, href) in &bad_anchors {
-            print_href_error("error: bad link", &href, *lineno);
+            for link in html_result.collector.get_external_link_usages(url) {
+                add_problem(
+                    &mut reporter,
+                    &paragraps_to_sourcefile,
+                    &ignore_rules,
+                    ProblemKind::BadExternalLink,
+                    link,
+                    Some(status.to_string()),
+                    Vec::new(),
+                )?;
+            }
         }
+    }
 
-        if github_actions {
-            if !bad_links.is_empty() {
-                print_github_actions_href_list("bad links", &*filepath, &bad_links)?;
-            }
+    let mut fixed_files = Vec::new();
+    if fix {
+        for problem in reporter.problems() {
+            fixes.add(problem);
+        }
 
-            if !bad_anchors.is_empty() {
-                print_github_actions_href_list("bad anchors", &*filepath, &bad_anchors)?;
+        fixed_files = fixes.apply()?;
+        if !dry_run {
+            for file in &fixed_files {
+                file.write()?;
             }
         }
 
-        println!();
+        reporter.progress(format_args!(
+            "{} {} links in {} files",
+            if dry_run { "Would fix" } else { "Fixed" },
+            fixed_files
+                .iter()
+                .map(|file| file.fixed_links)
+                .sum::<usize>(),
+            fixed_files.len()
+        ));
     }
 
-    println!("Found {} bad links", bad_links_count);
+    let exit_code = reporter.finish()?;
 
-    if check_anchors {
-        println!("Found {} bad anchors", bad_anchors_count);
+    // The diff is printed after the report, such that it can be told apart from it.
+    if dry_run {
+        for file in &fixed_files {
+            print!("\n{}", file.diff());
+        }
     }
 
     // We're about to exit the program and leaking the memory is faster than running drop
     mem::forget(html_result);
 
-    if bad_links_count > 0 {
-        process::exit(1);
-    }
-
-    if bad_anchors_count > 0 {
-        process::exit(2);
-    }
-
-    Ok(())
-}
-
-fn print_href_error(message: &'static str, href: &str, lineno: Option<usize>) {
-    if let Some(lineno) = lineno {
-        println!("  {} {} at line {}", message, href, lineno);
-    } else {
-        println!("  {} {}", message, href);
-    }
-}
-
-fn print_github_actions_href_list(
-    message: &'static str,
-    filepath: &Path,
-    hrefs: &BTreeSet<(Option<usize>, String)>,
-) -> Result<(), Error> {
-    let mut prev_lineno = None;
-    for (i, (lineno, href)) in hrefs.iter().enumerate() {
-        if prev_lineno != *lineno || i == 0 {
-            print!(
-                "\n::error file={},line={}::{}:",
-                filepath.canonicalize()?.display(),
-                lineno.unwrap_or(1),
-                message,
-            );
-        }
-        prev_lineno = *lineno;
-
-        // %0A -- escaped newline
-        //
-        // https://github.community/t/what-is-the-correct-character-escaping-for-workflow-command-values-e-g-echo-xxxx/118465/5
-        print!("%0A  {}", href);
+    if exit_code != 0 {
+        process::exit(exit_code);
     }
 
-    println!();
-
     Ok(())
 }
 
@@ -311,7 +936,7 @@ fn dump_paragraphs(path: PathBuf) -> Result<(), Error> {
             source
                 .paragraphs::<DebugParagraphWalker<ParagraphHasher>>()?
                 .into_iter()
-                .map(|(paragraph, lineno)| (paragraph, Some(lineno)))
+                .map(|(paragraph, lineno, _)| (paragraph, Some(lineno)))
                 .collect()
         }
         Some(x) if HTML_FILES.contains(&x) => {
@@ -321,8 +946,10 @@ fn dump_paragraphs(path: PathBuf) -> Result<(), Error> {
                 &arena,
                 &mut Vec::new(),
                 &mut links,
-                false,
-                true,
+                &LinkOptions {
+                    get_paragraphs: true,
+                    ..Default::default()
+                },
             )?;
             links
                 .into_iter()
@@ -345,8 +972,13 @@ fn dump_paragraphs(path: PathBuf) -> Result<(), Error> {
 
 struct HtmlResult<C> {
     collector: C,
-    documents_count: usize,
-    file_count: usize,
+    /// The HTML files that were checked, in no particular order.
+    documents: Vec<Arc<PathBuf>>,
+    /// All files in the static file path, including the HTML files, in no particular order. When
+    /// crawling, only the files that were reached.
+    files: Vec<Document>,
+    /// Files that cannot be reached from the entry points, when crawling or checking for orphans.
+    unreached: Vec<Document>,
 }
 
 fn walk_files(base_path: &Path) -> impl ParallelIterator<Item = jwalk::DirEntry<((), ())>> {
@@ -395,44 +1027,46 @@ fn walk_files(base_path: &Path) -> impl ParallelIterator<Item = jwalk::DirEntry<
     entries.into_par_iter().with_min_len(min_len)
 }
 
-fn extract_html_links<C: LinkCollector<P::Paragraph>, P: ParagraphWalker>(
-    base_path: &Path,
-    check_anchors: bool,
-    get_paragraphs: bool,
-) -> Result<HtmlResult<C>, Error> {
-    let result: Result<_, Error> = walk_files(base_path)
+/// Create the document for a file in the static file path, with the href it is served under.
+fn new_document(base_path: &Path, path: &Path, hosting: &HostingConfig) -> Document {
+    let mut document = Document::new(base_path, path);
+    if hosting.clean_urls {
+        document = document.with_clean_url();
+    }
+    match hosting.trailing_slash {
+        TrailingSlash::Always => document = document.with_trailing_slash(),
+        TrailingSlash::Never => document = document.without_trailing_slash(),
+        TrailingSlash::Ignore => (),
+    }
+    document
+}
+
+fn read_files(base_path: &Path, hosting: &HostingConfig) -> Vec<Document> {
+    walk_files(base_path)
+        .map(|entry| new_document(base_path, &entry.path(), hosting))
+        .collect()
+}
+
+/// Extract the links of HTML documents in parallel.
+fn extract_document_links<C: LinkCollector<P::Paragraph>, P: ParagraphWalker>(
+    documents: &[&Document],
+    options: &LinkOptions,
+) -> Result<C, Error> {
+    // Minimize amount of LinkCollector instances created. This impacts parallelism but
+    // `LinkCollector::merge` is rather slow.
+    let min_len = documents.len() / rayon::current_num_threads();
+
+    documents
+        .par_iter()
+        .with_min_len(min_len)
         .try_fold(
             // apparently can't use arena allocations here because that would make values !Send
             // also because quick-xml specifically wants std vec
-            || (bumpalo::Bump::new(), Vec::new(), C::new(), 0, 0),
-            |(mut arena, mut xml_buf, mut collector, mut documents_count, mut file_count),
-             entry| {
-                let path = entry.path();
-                let document = Document::new(&base_path, &path);
-
-                collector.ingest(Link::Defines(DefinedLink {
-                    href: document.href(),
-                }));
-                file_count += 1;
-
-                if !document
-                    .path
-                    .extension()
-                    .and_then(|extension| Some(HTML_FILES.contains(&extension.to_str()?)))
-                    .unwrap_or(false)
-                {
-                    return Ok((arena, xml_buf, collector, documents_count, file_count));
-                }
-
+            || (bumpalo::Bump::new(), Vec::new(), C::new()),
+            |(mut arena, mut xml_buf, mut collector), document| {
                 let mut link_buf = BumpVec::new_in(&arena);
                 document
-                    .links::<P>(
-                        &arena,
-                        &mut xml_buf,
-                        &mut link_buf,
-                        check_anchors,
-                        get_paragraphs,
-                    )
+                    .links::<P>(&arena, &mut xml_buf, &mut link_buf, options)
                     .with_context(|| format!("Failed to read file {}", document.path.display()))?;
 
                 xml_buf.clear();
@@ -443,59 +1077,172 @@ fn extract_html_links<C: LinkCollector<P::Paragraph>, P: ParagraphWalker>(
 
                 arena.reset();
 
-                documents_count += 1;
-
-                Ok((arena, xml_buf, collector, documents_count, file_count))
+                Ok((arena, xml_buf, collector))
             },
         )
-        .map(|result| {
-            result.map(|(_, _, collector, documents_count, file_count)| {
-                (collector, documents_count, file_count)
-            })
+        .map(|result| result.map(|(_, _, collector)| collector))
+        .try_reduce(C::new, |mut collector, collector2| {
+            collector.merge(collector2);
+            Ok(collector)
         })
-        .try_reduce(
-            || (C::new(), 0, 0),
-            |(mut collector, mut documents_count, mut file_count),
-             (collector2, documents_count2, file_count2)| {
-                collector.merge(collector2);
-                documents_count += documents_count2;
-                file_count += file_count2;
-                Ok((collector, documents_count, file_count))
-            },
-        );
+}
+
+fn extract_html_links<C: LinkCollector<P::Paragraph>, P: ParagraphWalker>(
+    base_path: &Path,
+    file_types: &FileTypes,
+    options: &LinkOptions,
+    hosting: &HostingConfig,
+) -> Result<HtmlResult<C>, Error> {
+    let files = read_files(base_path, hosting);
+
+    let documents: Vec<&Document> = files
+        .iter()
+        .filter(|document| file_types.is_html(&document.path))
+        .collect();
+    let mut collector: C = extract_document_links::<_, P>(&documents, options)?;
+    let documents = documents
+        .iter()
+        .map(|document| document.path.clone())
+        .collect();
 
-    let (collector, documents_count, file_count) = result?;
+    for file in &files {
+        collector.ingest(Link::Defines(DefinedLink { href: file.href() }));
+    }
+
+    Ok(HtmlResult {
+        collector,
+        documents,
+        files,
+        unreached: Vec::new(),
+    })
+}
+
+/// Like `extract_html_links`, but read documents that can be reached by following links from the
+/// entry points first, breadth-first. All other files end up in `HtmlResult::unreached`, and are
+/// only read if `read_unreached` is set.
+fn crawl_html_links<C: LinkCollector<P::Paragraph>, P: ParagraphWalker>(
+    base_path: &Path,
+    file_types: &FileTypes,
+    entry_points: &[Pattern],
+    options: &LinkOptions,
+    hosting: &HostingConfig,
+    read_unreached: bool,
+) -> Result<HtmlResult<C>, Error> {
+    let files = read_files(base_path, hosting);
+    let files_by_href: BTreeMap<&str, usize> = files
+        .iter()
+        .enumerate()
+        .map(|(i, file)| (file.href().0, i))
+        .collect();
+
+    let mut reached: Vec<bool> = files
+        .iter()
+        .map(|file| {
+            let path = site_path(base_path, &file.path);
+            entry_points.iter().any(|pattern| pattern.is_match(&path))
+        })
+        .collect();
+
+    // Targets of redirects and rewrites are requested without being linked to, too.
+    for target in hosting.rule_targets() {
+        match target {
+            RuleTarget::Href(href) => {
+                if let Some(&i) = find_target(&href, hosting, |href| files_by_href.get(href)) {
+                    reached[i] = true;
+                }
+            }
+            RuleTarget::Prefix(prefix) => {
+                for (i, file) in files.iter().enumerate() {
+                    if file.href().0.starts_with(&prefix) {
+                        reached[i] = true;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut frontier: Vec<usize> = (0..files.len()).filter(|&i| reached[i]).collect();
+
+    let mut collector = C::new();
+    let mut documents = Vec::new();
+
+    while !frontier.is_empty() {
+        let level: Vec<&Document> = frontier
+            .iter()
+            .map(|&i| &files[i])
+            .filter(|document| file_types.is_html(&document.path))
+            .collect();
+        let CrawlCollector {
+            collector: level_collector,
+            targets,
+        } = extract_document_links::<CrawlCollector<C>, P>(&level, options)?;
+        collector.merge(level_collector);
+        documents.extend(level.iter().map(|document| document.path.clone()));
+
+        frontier.clear();
+        for href in &targets {
+            if let Some(&i) = find_target(href, hosting, |href| files_by_href.get(href)) {
+                if !reached[i] {
+                    reached[i] = true;
+                    frontier.push(i);
+                }
+            }
+        }
+    }
+
+    for file in &files {
+        collector.ingest(Link::Defines(DefinedLink { href: file.href() }));
+    }
+
+    let mut reached_files = Vec::new();
+    let mut unreached = Vec::new();
+    for (file, reached) in files.into_iter().zip(reached) {
+        if reached {
+            reached_files.push(file);
+        } else {
+            unreached.push(file);
+        }
+    }
+
+    if read_unreached {
+        let rest: Vec<&Document> = unreached
+            .iter()
+            .filter(|document| file_types.is_html(&document.path))
+            .collect();
+        collector.merge(extract_document_links::<C, P>(&rest, options)?);
+        documents.extend(rest.iter().map(|document| document.path.clone()));
+        reached_files.extend(unreached.iter().cloned());
+    }
 
     Ok(HtmlResult {
         collector,
-        documents_count,
-        file_count,
+        documents,
+        files: reached_files,
+        unreached,
     })
 }
 
-type MarkdownResult<P> = BTreeMap<P, Vec<(DocumentSource, usize)>>;
+/// Markdown sources by paragraph, with the line number of the paragraph and whether problems in
+/// it are suppressed.
+type MarkdownResult<P> = BTreeMap<P, Vec<(DocumentSource, usize, bool)>>;
 
 fn extract_markdown_paragraphs<P: ParagraphWalker>(
     sources_path: &Path,
+    file_types: &FileTypes,
 ) -> Result<MarkdownResult<P::Paragraph>, Error> {
     let results: Vec<Result<_, Error>> = walk_files(sources_path)
         .try_fold(Vec::new, |mut paragraphs, entry| {
             let source = DocumentSource::new(entry.path());
 
-            if !source
-                .path
-                .extension()
-                .and_then(|extension| Some(MARKDOWN_FILES.contains(&extension.to_str()?)))
-                .unwrap_or(false)
-            {
+            if !file_types.is_markdown(&source.path) {
                 return Ok(paragraphs);
             }
 
-            for paragraph_and_lineno in source
+            for paragraph in source
                 .paragraphs::<P>()
                 .with_context(|| format!("Failed to read file {}", source.path.display()))?
             {
-                paragraphs.push((source.clone(), paragraph_and_lineno));
+                paragraphs.push((source.clone(), paragraph));
             }
             Ok(paragraphs)
         })
@@ -504,11 +1251,11 @@ fn extract_markdown_paragraphs<P: ParagraphWalker>(
     let mut paragraps_to_sourcefile = BTreeMap::new();
 
     for result in results {
-        for (source, (paragraph, lineno)) in result? {
+        for (source, (paragraph, lineno, ignored)) in result? {
             paragraps_to_sourcefile
                 .entry(paragraph)
                 .or_insert_with(Vec::new)
-                .push((source.clone(), lineno));
+                .push((source.clone(), lineno, ignored));
         }
     }
 
@@ -516,12 +1263,24 @@ fn extract_markdown_paragraphs<P: ParagraphWalker>(
 }
 
 fn match_all_paragraphs(base_path: PathBuf, sources_path: PathBuf) -> Result<(), Error> {
+    let file_types = FileTypes::default();
+
     println!("Reading files");
-    let html_result =
-        extract_html_links::<UsedLinkCollector<_>, ParagraphHasher>(&base_path, true, true)?;
+    let html_result = extract_html_links::<UsedLinkCollector<_>, ParagraphHasher>(
+        &base_path,
+        &file_types,
+        &LinkOptions {
+            check_anchors: true,
+            check_external: false,
+            get_paragraphs: true,
+            ..Default::default()
+        },
+        &HostingConfig::default(),
+    )?;
 
     println!("Reading source files");
-    let paragraps_to_sourcefile = extract_markdown_paragraphs::<ParagraphHasher>(&sources_path)?;
+    let paragraps_to_sourcefile =
+        extract_markdown_paragraphs::<ParagraphHasher>(&sources_path, &file_types)?;
 
     println!("Calculating");
     let mut total_links = 0;
@@ -573,13 +1332,133 @@ fn match_all_paragraphs(base_path: PathBuf, sources_path: PathBuf) -> Result<(),
     Ok(())
 }
 
+fn graph(
+    base_path: &Path,
+    format: GraphFormat,
+    file_types: &FileTypes,
+    options: &LinkOptions,
+    hosting: &HostingConfig,
+) -> Result<(), Error> {
+    let html_result = extract_html_links::<GraphCollector, NoopParagraphWalker>(
+        base_path, file_types, options, hosting,
+    )?;
+
+    let graph = Graph::new(
+        html_result.collector,
+        &html_result.files,
+        &html_result.documents,
+        base_path,
+        hosting,
+    );
+
+    match format {
+        GraphFormat::Dot => print!("{}", graph.dot()),
+        GraphFormat::Graphml => print!("{}", graph.graphml()),
+        GraphFormat::Json => println!("{}", serde_json::to_string_pretty(&graph)?),
+    }
+
+    Ok(())
+}
+
+fn stats(
+    base_path: &Path,
+    format: StatsFormat,
+    top: usize,
+    file_types: &FileTypes,
+    options: &LinkOptions,
+    hosting: &HostingConfig,
+) -> Result<(), Error> {
+    let html_result = extract_html_links::<StatsCollector, NoopParagraphWalker>(
+        base_path, file_types, options, hosting,
+    )?;
+
+    let stats = Stats::new(
+        &html_result.collector,
+        &html_result.files,
+        &html_result.documents,
+        base_path,
+        hosting,
+        top,
+    );
+
+    match format {
+        StatsFormat::Text => print!("{}", stats.text()),
+        StatsFormat::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
     use assert_cmd::Command;
     use assert_fs::prelude::*;
     use predicates::prelude::*;
 
-    #[test]
+    /// Serve fixed responses on a local port as a stand-in for external servers. Routes are
+    /// `(method, path, status)`, where a method of `*` matches any method. Unknown routes respond
+    /// with 404. A status of 0 closes the connection without a response, and 429 responses ask to
+    /// retry after one second.
+    ///
+    /// Returns the base URL and a counter of received requests.
+    fn serve_http(
+        routes: &'static [(&'static str, &'static str, u16)],
+    ) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests_count = Arc::new(AtomicUsize::new(0));
+        let requests_count2 = requests_count.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+
+                // skip headers
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+
+                requests_count2.fetch_add(1, Ordering::SeqCst);
+
+                let mut parts = request_line.split(' ');
+                let method = parts.next().unwrap();
+                let path = parts.next().unwrap();
+                let status = routes
+                    .iter()
+                    .find(|(m, p, _)| (*m == "*" || *m == method) && *p == path)
+                    .map(|(_, _, status)| *status)
+                    .unwrap_or(404);
+
+                let headers = match status {
+                    0 => continue,
+                    429 => "Retry-After: 1\r\n",
+                    _ => "",
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {} Whatever\r\n{}Content-Length: 0\r\nConnection: close\r\n\r\n",
+                    status, headers
+                )
+                .unwrap();
+            }
+        });
+
+        (url, requests_count)
+    }
+
+    #[test]
     fn test_dead_link() {
         let site = assert_fs::TempDir::new().unwrap();
         site.child("index.html")
@@ -623,6 +1502,1347 @@ Found 1 bad anchors
         site.close().unwrap();
     }
 
+    #[test]
+    fn test_suggestions() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str(
+                "<a href=docs/instal.html><a href=faq.html><a href=Blog/><a href=guide/intro.html#instalation><a href=about.html>",
+            )
+            .unwrap();
+        site.child("docs/install.html").touch().unwrap();
+        site.child("help/faq.html").touch().unwrap();
+        site.child("blog/index.html").touch().unwrap();
+        site.child("guide/intro.html")
+            .write_str("<h2 id=installation><h2 id=usage>")
+            .unwrap();
+
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--check-anchors")
+            .arg("--suggestions")
+            .arg("3");
+
+        cmd.assert().failure().code(1).stdout(
+            r#"Reading files
+Checking 5 links from 5 files (5 documents)
+./index.html
+  error: bad link Blog (did you mean blog?)
+  error: bad link about.html
+  error: bad link docs/instal.html (did you mean docs/install.html?)
+  error: bad link faq.html (did you mean help/faq.html?)
+  error: bad link guide/intro.html#instalation (did you mean guide/intro.html#installation?)
+
+Found 4 bad links
+Found 1 bad anchors
+"#,
+        );
+
+        // Suggestions are opt-in.
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path()).arg(".");
+
+        cmd.assert().failure().code(1).stdout(
+            r#"Reading files
+Checking 5 links from 5 files (5 documents)
+./index.html
+  error: bad link Blog
+  error: bad link about.html
+  error: bad link docs/instal.html
+  error: bad link faq.html
+
+Found 4 bad links
+"#,
+        );
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_fix() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str(
+                "<p>See <a href=docs/instal.html>install</a></p><p>Read the <a href=faq.html>faq</a></p><p>Follow the <a href=guide.html>guide</a></p>",
+            )
+            .unwrap();
+        site.child("docs/install.html").touch().unwrap();
+        site.child("help/faq.html").touch().unwrap();
+        site.child("a/guide.html").touch().unwrap();
+        site.child("b/guide.html").touch().unwrap();
+        site.child("src/index.md")
+            .write_str(
+                "See [install](./docs/instal.html)\n\nRead the [faq](faq.html \"faq.html\")\n\nFollow the [guide](guide.html)\n",
+            )
+            .unwrap();
+
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path()).arg(".").arg("--fix");
+        cmd.assert()
+            .failure()
+            .code(1)
+            .stderr(predicate::str::starts_with(
+                "Error: --fix requires --sources\n",
+            ));
+
+        // Links with more than one candidate are not fixed, even if only one is suggested.
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--sources")
+            .arg("src")
+            .arg("--fix")
+            .arg("--dry-run")
+            .arg("--suggestions")
+            .arg("1");
+
+        cmd.assert().failure().code(1).stdout(
+            r#"Reading files
+Reading source files
+Checking 3 links from 6 files (5 documents)
+Would fix 2 links in 1 files
+src/index.md
+  error: bad link docs/instal.html (did you mean docs/install.html?) at line 1
+  error: bad link faq.html (did you mean help/faq.html?) at line 3
+  error: bad link guide.html (did you mean a/guide.html?) at line 5
+
+Found 3 bad links
+
+--- src/index.md
++++ src/index.md
+@@ -1,5 +1,5 @@
+-See [install](./docs/instal.html)
++See [install](./docs/install.html)
+ 
+-Read the [faq](faq.html "faq.html")
++Read the [faq](help/faq.html "faq.html")
+ 
+ Follow the [guide](guide.html)
+"#,
+        );
+        site.child("src/index.md")
+            .assert("See [install](./docs/instal.html)\n\nRead the [faq](faq.html \"faq.html\")\n\nFollow the [guide](guide.html)\n");
+
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--sources")
+            .arg("src")
+            .arg("--fix");
+
+        cmd.assert()
+            .failure()
+            .code(1)
+            .stdout(predicate::str::contains("Fixed 2 links in 1 files\n"));
+        site.child("src/index.md")
+            .assert("See [install](./docs/install.html)\n\nRead the [faq](help/faq.html \"faq.html\")\n\nFollow the [guide](guide.html)\n");
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_netlify_redirects() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str("<a href=/old/page.html><a href=/docs/><a href=/missing>")
+            .unwrap();
+        site.child("docs/index.html").touch().unwrap();
+        site.child("_redirects")
+            .write_str("/old/*  /docs/:splat  301\n/legacy  /gone/  301\n")
+            .unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path()).arg(".");
+
+        cmd.assert().failure().code(1).stdout(
+            r#"Reading files
+Checking 3 links from 3 files (2 documents)
+./_redirects
+  error: bad redirect /gone/ at line 2
+
+./index.html
+  error: bad link missing
+
+Found 1 bad links
+Found 1 bad redirects
+"#,
+        );
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_vercel_config() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("public/index.html")
+            .write_str("<a href=/about><a href=/blog/hello><a href=/missing>")
+            .unwrap();
+        site.child("public/about.html").touch().unwrap();
+        site.child("vercel.json")
+            .write_str(
+                r#"{
+  "cleanUrls": true,
+  "rewrites": [{ "source": "/blog/:slug", "destination": "/post.html" }]
+}"#,
+            )
+            .unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg("public/")
+            .arg("--hosting-config=vercel.json");
+
+        cmd.assert().failure().code(1).stdout(
+            r#"Reading files
+Checking 3 links from 2 files (2 documents)
+public/index.html
+  error: bad link missing
+
+vercel.json
+  error: bad redirect /post.html at line 3
+
+Found 1 bad links
+Found 1 bad redirects
+"#,
+        );
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_clean_urls() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str(
+                "<a href=/docs/install><a href=docs/install.html#usage><a href=/docs/missing>",
+            )
+            .unwrap();
+        site.child("docs/install.html")
+            .write_str("<h2 id=usage>")
+            .unwrap();
+
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path()).arg(".").arg("--clean-urls");
+
+        cmd.assert().failure().code(1).stdout(
+            r#"Reading files
+Checking 3 links from 2 files (2 documents)
+./index.html
+  error: bad link docs/missing
+
+Found 1 bad links
+"#,
+        );
+
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--enforce-clean-urls")
+            .arg("--check-anchors");
+
+        cmd.assert().failure().code(1).stdout(
+            r#"Reading files
+Checking 3 links from 2 files (2 documents)
+./index.html
+  error: bad link docs/missing
+  warning: link with .html extension docs/install.html#usage
+
+Found 1 bad links
+Found 0 bad anchors
+Found 1 links with .html extension
+"#,
+        );
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_trailing_slash() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str(
+                r#"<a href="/docs/"><a href="/docs"><a href="/about.html/"><a href="/missing/">"#,
+            )
+            .unwrap();
+        site.child("docs/index.html")
+            .write_str(r#"<a href="./"><a href="../about.html">"#)
+            .unwrap();
+        site.child("about.html").touch().unwrap();
+
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--trailing-slash=always");
+
+        cmd.assert().failure().code(1).stdout(
+            r#"Reading files
+Checking 6 links from 3 files (3 documents)
+./index.html
+  error: bad link missing/
+  error: bad trailing slash about.html/
+  error: bad trailing slash docs
+
+Found 1 bad links
+Found 2 bad trailing slashes
+"#,
+        );
+
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--trailing-slash=never");
+
+        cmd.assert().failure().code(1).stdout(
+            r#"Reading files
+Checking 6 links from 3 files (3 documents)
+./index.html
+  error: bad link missing/
+  error: bad trailing slash about.html/
+  error: bad trailing slash docs/
+
+Found 1 bad links
+Found 2 bad trailing slashes
+"#,
+        );
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_trailing_slash_anchors() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str(r#"<a href="/docs/#usage"><a href="/docs/#missing">"#)
+            .unwrap();
+        site.child("docs/index.html")
+            .write_str(r##"<h2 id="usage">Usage</h2><a href="#usage"><a href="./#usage">"##)
+            .unwrap();
+
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--trailing-slash=always")
+            .arg("--check-anchors");
+
+        cmd.assert().failure().code(2).stdout(
+            r#"Reading files
+Checking 4 links from 2 files (2 documents)
+./index.html
+  error: bad link docs/#missing
+
+Found 0 bad links
+Found 0 bad trailing slashes
+Found 1 bad anchors
+"#,
+        );
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_base_url() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str(
+                r#"<a href="/docs/install.html"><a href="/docs"><a href="/blog/">
+                <a href="https://example.com/docs/missing.html"><a href="https://example.com/">"#,
+            )
+            .unwrap();
+        site.child("install.html").touch().unwrap();
+
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--base-url=https://example.com/docs/");
+
+        cmd.assert().failure().code(1).stdout(
+            r#"Reading files
+Checking 3 links from 2 files (2 documents)
+./index.html
+  error: bad link missing.html
+
+Found 1 bad links
+"#,
+        );
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_internal_domain() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str(
+                r#"<a href="https://docs.example.com/install/#usage">
+                <a href="https://docs.example.com/install/#missing">
+                <a href="http://DOCS.example.com/missing/">
+                <a href="https://example.com/missing/">"#,
+            )
+            .unwrap();
+        site.child("install/index.html")
+            .write_str("<h2 id=usage>")
+            .unwrap();
+
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--check-anchors")
+            .arg("--internal-domain=docs.example.com");
+
+        cmd.assert().failure().code(1).stdout(
+            r#"Reading files
+Checking 3 links from 2 files (2 documents)
+./index.html
+  error: bad link missing
+  error: bad link install#missing
+
+Found 1 bad links
+Found 1 bad anchors
+"#,
+        );
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_format_json() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str("<a href=bar.html#goo><a href=baz.html>")
+            .unwrap();
+        site.child("bar.html").touch().unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--check-anchors")
+            .arg("--format")
+            .arg("json");
+
+        cmd.assert()
+            .failure()
+            .code(1)
+            .stdout(
+                r#"{
+  "problems": [
+    {
+      "kind": "bad-link",
+      "href": "baz.html",
+      "path": "./index.html",
+      "lineno": null,
+      "sources": []
+    },
+    {
+      "kind": "bad-anchor",
+      "href": "bar.html#goo",
+      "path": "./index.html",
+      "lineno": null,
+      "sources": []
+    }
+  ],
+  "summary": {
+    "links": 2,
+    "files": 2,
+    "documents": 2,
+    "suppressed": 0,
+    "problems": {
+      "bad-link": 1,
+      "bad-anchor": 1
+    }
+  }
+}
+"#,
+            )
+            .stderr(
+                r#"Reading files
+Checking 2 links from 2 files (2 documents)
+"#,
+            );
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_format_ndjson() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str("<p>Hello <a href=bar.html>world</a></p>")
+            .unwrap();
+        site.child("src/index.md")
+            .write_str("Hello [world](bar.html)\n")
+            .unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--sources")
+            .arg("src")
+            .arg("--format")
+            .arg("ndjson");
+
+        cmd.assert().failure().code(1).stdout(
+            r#"{"type":"problem","kind":"bad-link","href":"bar.html","path":"./index.html","lineno":null,"sources":[{"path":"src/index.md","lineno":1}]}
+{"type":"summary","links":1,"files":2,"documents":1,"suppressed":0,"problems":{"bad-link":1}}
+"#,
+        );
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_format_sarif() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str("<p>Hello <a href=bar.html#goo>world</a></p><a href=baz.html>")
+            .unwrap();
+        site.child("bar.html").touch().unwrap();
+        site.child("src/index.md")
+            .write_str("Hello [world](bar.html#goo)\n")
+            .unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--check-anchors")
+            .arg("--sources")
+            .arg("src")
+            .arg("--format")
+            .arg("sarif");
+
+        let output = cmd.assert().failure().code(1).get_output().stdout.clone();
+        let sarif: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        let run = &sarif["runs"][0];
+
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "bad-link");
+        assert_eq!(run["tool"]["driver"]["rules"][1]["id"], "bad-anchor");
+        assert_eq!(
+            run["results"],
+            serde_json::json!([
+                {
+                    "ruleId": "bad-link",
+                    "ruleIndex": 0,
+                    "level": "error",
+                    "message": { "text": "Bad link: baz.html" },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": "index.html" },
+                        },
+                    }],
+                },
+                {
+                    "ruleId": "bad-anchor",
+                    "ruleIndex": 1,
+                    "level": "warning",
+                    "message": { "text": "Bad anchor: bar.html#goo" },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": "src/index.md" },
+                            "region": { "startLine": 1 },
+                        },
+                    }],
+                },
+            ])
+        );
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_format_junit() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str("<a href=bar.html#goo><a href=\"a&b.html\">")
+            .unwrap();
+        site.child("bar.html").touch().unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--check-anchors")
+            .arg("--format")
+            .arg("junit");
+
+        cmd.assert().failure().code(1).stdout(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="hyperlink" tests="4" failures="2">
+  <testsuite name="bad links" tests="2" failures="1">
+    <testcase name="./bar.html" classname="bad-link"/>
+    <testcase name="./index.html" classname="bad-link">
+      <failure type="bad-link" message="1 bad links">
+a&amp;b.html
+      </failure>
+    </testcase>
+  </testsuite>
+  <testsuite name="bad anchors" tests="2" failures="1">
+    <testcase name="./bar.html" classname="bad-anchor"/>
+    <testcase name="./index.html" classname="bad-anchor">
+      <failure type="bad-anchor" message="1 bad anchors">
+bar.html#goo
+      </failure>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#,
+        );
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_format_gitlab() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str(
+                "<p>Hello <a href=bar.html>world</a></p><p>Bye <a href=bar.html>world</a></p>",
+            )
+            .unwrap();
+        site.child("src/index.md")
+            .write_str("Hello [world](bar.html)\n\nBye [world](bar.html)\n")
+            .unwrap();
+
+        let run_in = |dir: &Path, path: &Path| {
+            let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+            cmd.current_dir(dir)
+                .arg(path)
+                .arg("--sources")
+                .arg(path.join("src"))
+                .arg("--format")
+                .arg("gitlab");
+            let output = cmd.assert().failure().code(1).get_output().stdout.clone();
+            let mut report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+            let fingerprints: Vec<_> = report
+                .as_array_mut()
+                .unwrap()
+                .iter_mut()
+                .map(|issue| {
+                    issue
+                        .as_object_mut()
+                        .unwrap()
+                        .remove("fingerprint")
+                        .unwrap()
+                })
+                .collect();
+            (report, fingerprints)
+        };
+        let run = || run_in(site.path(), Path::new("."));
+
+        let (report, fingerprints) = run();
+        assert_eq!(
+            report,
+            serde_json::json!([
+                {
+                    "description": "Bad link: bar.html",
+                    "check_name": "bad-link",
+                    "severity": "major",
+                    "location": { "path": "src/index.md", "lines": { "begin": 1 } },
+                },
+                {
+                    "description": "Bad link: bar.html",
+                    "check_name": "bad-link",
+                    "severity": "major",
+                    "location": { "path": "src/index.md", "lines": { "begin": 3 } },
+                },
+            ])
+        );
+        assert_ne!(fingerprints[0], fingerprints[1]);
+
+        // fingerprints do not change when lines move
+        site.child("src/index.md")
+            .write_str("# Title\n\nHello [world](bar.html)\n\nBye [world](bar.html)\n")
+            .unwrap();
+        let (_, fingerprints2) = run();
+        assert_eq!(fingerprints, fingerprints2);
+
+        // fingerprints do not change with the working directory
+        let (_, fingerprints3) = run_in(
+            site.path().parent().unwrap(),
+            Path::new(site.path().file_name().unwrap()),
+        );
+        assert_eq!(fingerprints, fingerprints3);
+
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_github_actions() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str(
+                "<p>Hello <a href=bar.html>world</a></p><p>Bye <a href=baz.html#goo>world</a></p>",
+            )
+            .unwrap();
+        site.child("baz.html").touch().unwrap();
+        site.child("src/index.md")
+            .write_str("Hello [world](bar.html)\n\nBye [world](baz.html#goo)\n")
+            .unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .env_remove("GITHUB_STEP_SUMMARY")
+            .arg(".")
+            .arg("--sources")
+            .arg("src")
+            .arg("--check-anchors")
+            .arg("--github-actions");
+
+        let source = site.path().join("src/index.md").canonicalize().unwrap();
+        cmd.assert().failure().code(1).stdout(format!(
+            r#"Reading files
+Reading source files
+Checking 2 links from 3 files (2 documents)
+src/index.md
+  error: bad link bar.html at line 1
+  error: bad link baz.html#goo at line 3
+
+::error file={0},line=1,endLine=1,title=Bad link::bad links:%0A  bar.html
+
+::warning file={0},line=3,endLine=3,title=Bad anchor::bad anchors:%0A  baz.html#goo
+
+Found 1 bad links
+Found 1 bad anchors
+"#,
+            source
+                .display()
+                .to_string()
+                .replace(':', "%3A")
+                .replace(',', "%2C")
+        ));
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_github_step_summary() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str("<p>Hello <a href=bar.html>world</a></p><a href=\"a|b.html\">")
+            .unwrap();
+        site.child("src/index.md")
+            .write_str("Hello [world](bar.html)\n")
+            .unwrap();
+        let step_summary = site.child("step_summary.md");
+        step_summary.write_str("# Previous step\n").unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .env("GITHUB_STEP_SUMMARY", step_summary.path())
+            .arg(".")
+            .arg("--sources")
+            .arg("src")
+            .arg("--github-actions");
+
+        cmd.assert().failure().code(1);
+        step_summary.assert(
+            r#"# Previous step
+## hyperlink
+
+Checked 2 links from 3 files (1 documents). Found 2 bad links.
+
+### `src/index.md` (1 problem)
+
+| Line | Problem | Link |
+| --- | --- | --- |
+| 1 | Bad link | `bar.html` |
+
+### `index.html` (1 problem)
+
+| Line | Problem | Link |
+| --- | --- | --- |
+|  | Bad link | `a\|b.html` |
+"#,
+        );
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_config() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("hyperlink.toml")
+            .write_str(
+                r#"
+base-path = "public"
+check-anchors = true
+html-extensions = ["xhtml"]
+
+[severity]
+bad-anchor = "error"
+"#,
+            )
+            .unwrap();
+        site.child("public/index.xhtml")
+            .write_str("<a href=bar.xhtml#goo>")
+            .unwrap();
+        site.child("public/bar.xhtml").touch().unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path());
+
+        cmd.assert().failure().code(1).stdout(
+            r#"Reading files
+Checking 1 links from 2 files (2 documents)
+public/index.xhtml
+  error: bad link bar.xhtml#goo
+
+Found 0 bad links
+Found 1 bad anchors
+"#,
+        );
+
+        // command line options take precedence
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg("public")
+            .arg("--format")
+            .arg("ndjson");
+
+        cmd.assert().failure().code(1).stdout(
+            r#"{"type":"problem","kind":"bad-anchor","href":"bar.xhtml#goo","path":"public/index.xhtml","lineno":null,"sources":[]}
+{"type":"summary","links":1,"files":2,"documents":2,"suppressed":0,"problems":{"bad-link":0,"bad-anchor":1}}
+"#,
+        );
+
+        // the configuration file is also discovered in the static file path
+        site.child("public/hyperlink.toml")
+            .write_str("html-extensions = [\"xhtml\"]\n[severity]\nbad-link = \"warning\"")
+            .unwrap();
+        site.child("public/index.xhtml")
+            .write_str("<a href=baz.xhtml>")
+            .unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path()).arg("public");
+
+        cmd.assert()
+            .failure()
+            .code(2)
+            .stdout(predicate::str::contains("  warning: bad link baz.xhtml\n"));
+
+        // flags enabled in the configuration file can be disabled on the command line
+        site.child("public/index.xhtml")
+            .write_str("<a href=bar.xhtml#goo>")
+            .unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg("--config=hyperlink.toml")
+            .arg("--no-check-anchors");
+
+        cmd.assert().success().stdout(
+            r#"Reading files
+Checking 1 links from 3 files (2 documents)
+Found 0 bad links
+"#,
+        );
+
+        // the last of --check-anchors and --no-check-anchors wins
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg("--config=hyperlink.toml")
+            .arg("--no-check-anchors")
+            .arg("--check-anchors");
+
+        cmd.assert().failure().code(1);
+
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_ignore_rules() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str(r#"<a href="blog/2014/gone.html"><a href="../archive/old.html"><a href="missing.html">"#)
+            .unwrap();
+        site.child("vendor/changelog.html")
+            .write_str("<a href=missing.html>")
+            .unwrap();
+        site.child("hyperlink.toml")
+            .write_str(r#"ignore-paths = ["vendor/**"]"#)
+            .unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--ignore-href")
+            .arg("blog/*/gone.html")
+            .arg("--ignore-raw-href")
+            .arg("regex:^\\.\\./archive/");
+
+        cmd.assert().failure().code(1).stdout(
+            r#"Reading files
+Checking 4 links from 3 files (2 documents)
+./index.html
+  error: bad link missing.html
+
+Found 1 bad links
+Ignored 3 problems
+"#,
+        );
+
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--ignore-href")
+            .arg("**");
+
+        cmd.assert().success().stdout(
+            r#"Reading files
+Checking 4 links from 3 files (2 documents)
+Found 0 bad links
+Ignored 4 problems
+"#,
+        );
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_ignore_comments() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str(
+                "<p>Hello <a href=bar.html>world</a></p>\
+                 <p>Bye <a href=baz.html>world</a></p>\
+                 <!-- hyperlink-ignore-next --><p>Hi <a href=qux.html>there</a></p>",
+            )
+            .unwrap();
+        site.child("src/index.md")
+            .write_str(
+                "<!-- hyperlink-ignore-next -->\n\
+                 Hello [world](bar.html)\n\n\
+                 Bye [world](baz.html)\n",
+            )
+            .unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--sources")
+            .arg("src");
+
+        cmd.assert().failure().code(1).stdout(
+            r#"Reading files
+Reading source files
+Checking 3 links from 2 files (1 documents)
+src/index.md
+  error: bad link baz.html at line 3
+
+Found 1 bad links
+Ignored 2 problems
+"#,
+        );
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_check_orphans() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str("<a href=#top><a href=a.html><img src=img.png>")
+            .unwrap();
+        site.child("a.html")
+            .write_str("<a href=a.html><a href=index.html>")
+            .unwrap();
+        site.child("b.html")
+            .write_str("<a href=b.html#foo><a href=c.html>")
+            .unwrap();
+        site.child("c.html").touch().unwrap();
+        site.child("img.png").touch().unwrap();
+        site.child("unused.png").touch().unwrap();
+        site.child("404.html").touch().unwrap();
+        site.child("drafts/post.html").touch().unwrap();
+        site.child("moved.html").touch().unwrap();
+        site.child("archive/2019.html").touch().unwrap();
+        site.child("_redirects")
+            .write_str("/old  /moved.html  301\n/2019/*  /archive/:splat  301\n")
+            .unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--check-orphans")
+            .arg("--entry-point")
+            .arg("drafts/**");
+
+        cmd.assert().failure().code(4).stdout(
+            r#"Reading files
+Checking 7 links from 11 files (8 documents)
+./b.html
+  warning: orphaned file b.html
+
+./c.html
+  warning: orphaned file c.html
+
+Found 0 bad links
+Found 0 bad redirects
+Found 2 orphaned files
+"#,
+        );
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_crawl() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str("<a href=a.html>")
+            .unwrap();
+        site.child("a.html")
+            .write_str("<a href=b.html#intro><a href=missing.html>")
+            .unwrap();
+        site.child("b.html").write_str("<a href=./>").unwrap();
+        site.child("landing.html")
+            .write_str("<a href=c.html>")
+            .unwrap();
+        site.child("c.html").touch().unwrap();
+        site.child("stale.html")
+            .write_str("<a href=missing.html>")
+            .unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--crawl")
+            .arg("--entry-point")
+            .arg("landing.html");
+
+        cmd.assert().failure().code(1).stdout(
+            r#"Reading files
+Checking 5 links from 5 files (5 documents)
+./a.html
+  error: bad link missing.html
+
+./stale.html
+  warning: unreachable file stale.html
+
+Found 1 bad links
+Found 1 unreachable files
+"#,
+        );
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_graph() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str("<a href=a.html><a href=a.html#intro><img src=logo.png>")
+            .unwrap();
+        site.child("a.html")
+            .write_str("<h1 id=intro><a href=./><a href=missing.html>")
+            .unwrap();
+        site.child("logo.png").touch().unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path()).arg("graph").arg(".");
+
+        cmd.assert().success().stdout(
+            r#"digraph hyperlink {
+  "/" [shape=box];
+  "/a.html" [shape=box];
+  "/logo.png" [shape=ellipse];
+  "/missing.html" [shape=box, style=dashed, color=red];
+  "/" -> "/a.html" [label="a[href]"];
+  "/" -> "/logo.png" [label="img[src]"];
+  "/a.html" -> "/" [label="a[href]"];
+  "/a.html" -> "/missing.html" [label="a[href]"];
+}
+"#,
+        );
+
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg("graph")
+            .arg(".")
+            .arg("--check-anchors")
+            .arg("--format")
+            .arg("json");
+
+        cmd.assert().success().stdout(
+            r#"{
+  "nodes": [
+    {
+      "id": "/",
+      "kind": "document",
+      "path": "index.html"
+    },
+    {
+      "id": "/a.html",
+      "kind": "document",
+      "path": "a.html"
+    },
+    {
+      "id": "/a.html#intro",
+      "kind": "anchor",
+      "path": "a.html"
+    },
+    {
+      "id": "/logo.png",
+      "kind": "asset",
+      "path": "logo.png"
+    },
+    {
+      "id": "/missing.html",
+      "kind": "missing"
+    }
+  ],
+  "edges": [
+    {
+      "source": "/",
+      "target": "/a.html",
+      "element": "a",
+      "attribute": "href",
+      "count": 1
+    },
+    {
+      "source": "/",
+      "target": "/a.html#intro",
+      "element": "a",
+      "attribute": "href",
+      "count": 1
+    },
+    {
+      "source": "/",
+      "target": "/logo.png",
+      "element": "img",
+      "attribute": "src",
+      "count": 1
+    },
+    {
+      "source": "/a.html",
+      "target": "/",
+      "element": "a",
+      "attribute": "href",
+      "count": 1
+    },
+    {
+      "source": "/a.html",
+      "target": "/a.html#intro",
+      "element": "",
+      "attribute": "id",
+      "count": 1
+    },
+    {
+      "source": "/a.html",
+      "target": "/missing.html",
+      "element": "a",
+      "attribute": "href",
+      "count": 1
+    }
+  ]
+}
+"#,
+        );
+
+        // Links are resolved the same way as when checking them.
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg("--clean-urls")
+            .arg("graph")
+            .arg(".");
+
+        cmd.assert().success().stdout(
+            r#"digraph hyperlink {
+  "/" [shape=box];
+  "/a" [shape=box];
+  "/logo.png" [shape=ellipse];
+  "/missing.html" [shape=box, style=dashed, color=red];
+  "/" -> "/a" [label="a[href]"];
+  "/" -> "/logo.png" [label="img[src]"];
+  "/a" -> "/" [label="a[href]"];
+  "/a" -> "/missing.html" [label="a[href]"];
+}
+"#,
+        );
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_stats() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str("<a href=a.html><a href=#top><img src=logo.png>")
+            .unwrap();
+        site.child("a.html")
+            .write_str("<a href=b/><a href=./>")
+            .unwrap();
+        site.child("b/index.html")
+            .write_str("<a href=../a.html>")
+            .unwrap();
+        site.child("c.html").touch().unwrap();
+        site.child("logo.png").touch().unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg("stats")
+            .arg(".")
+            .arg("--top")
+            .arg("2");
+
+        cmd.assert().success().stdout(
+            r#"Documents      4
+Assets         1
+Links          6
+Unique hrefs   4
+Max depth      2
+
+Assets by extension
+  png          1
+
+Most linked documents
+  a.html
+  b/index.html
+
+Documents without outgoing links
+  c.html
+
+    In    Out  Depth  Document
+     2      2      1  a.html
+     1      1      2  b/index.html
+     0      0      -  c.html
+     1      2      0  index.html
+"#,
+        );
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_baseline() {
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("public/index.html")
+            .write_str("<a href=missing.html><a href=gone.html>")
+            .unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg("public")
+            .arg("--write-baseline")
+            .arg("baseline.json");
+
+        cmd.assert().failure().code(1).stdout(
+            r#"Reading files
+Checking 2 links from 1 files (1 documents)
+Wrote 2 problems to baseline baseline.json
+public/index.html
+  error: bad link gone.html
+  error: bad link missing.html
+
+Found 2 bad links
+"#,
+        );
+
+        site.child("public/index.html")
+            .write_str("<a href=missing.html><a href=new.html>")
+            .unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg("public")
+            .arg("--baseline")
+            .arg("baseline.json");
+
+        cmd.assert().failure().code(1).stdout(
+            r#"Reading files
+Checking 2 links from 1 files (1 documents)
+public/index.html
+  error: bad link new.html
+
+Found 1 bad links
+Accepted 1 problems from baseline
+Fixed 1 problems from baseline, they can be removed from it:
+  index.html: bad-link gone.html
+"#,
+        );
+
+        site.child("public/index.html")
+            .write_str("<a href=missing.html>")
+            .unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg("public")
+            .arg("--baseline")
+            .arg("baseline.json")
+            .arg("--format")
+            .arg("ndjson");
+
+        cmd.assert().success().stdout(
+            r#"{"type":"summary","links":1,"files":1,"documents":1,"suppressed":0,"baselined":1,"fixed":[{"path":"index.html","kind":"bad-link","href":"gone.html"}],"problems":{"bad-link":0}}
+"#,
+        );
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_external_links() {
+        let (url, _) = serve_http(&[
+            ("*", "/ok", 200),
+            ("HEAD", "/no-head", 405),
+            ("GET", "/no-head", 200),
+        ]);
+
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str(&format!(
+                "<a href={0}/ok><a href={0}/no-head#foo><a href={0}/missing><a href={0}/ok>",
+                url
+            ))
+            .unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--check-external")
+            .arg("--external-retries=0");
+
+        cmd.assert().failure().code(3).stdout(format!(
+            r#"Reading files
+Checking 0 links from 1 files (1 documents)
+Checking 3 external links
+./index.html
+  warning: bad external link {}/missing (HTTP 404)
+
+Found 0 bad links
+Found 1 bad external links
+"#,
+            url
+        ));
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_external_links_connection_error() {
+        let (url, requests_count) = serve_http(&[("*", "/reset", 0)]);
+
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str(&format!("<a href={}/reset>", url))
+            .unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--check-external")
+            .arg("--external-retries=0");
+
+        cmd.assert().failure().code(3);
+        // no GET after HEAD failed without a response
+        assert_eq!(requests_count.load(Ordering::SeqCst), 1);
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_external_links_retry_after() {
+        let (url, requests_count) = serve_http(&[("*", "/busy", 429)]);
+
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str(&format!("<a href={}/busy>", url))
+            .unwrap();
+        let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+        cmd.current_dir(site.path())
+            .arg(".")
+            .arg("--check-external")
+            .arg("--external-retries=1");
+
+        let start = Instant::now();
+        cmd.assert()
+            .failure()
+            .code(3)
+            .stdout(predicate::str::contains("(HTTP 429)"));
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        assert_eq!(requests_count.load(Ordering::SeqCst), 4);
+        site.close().unwrap();
+    }
+
+    #[test]
+    fn test_external_links_cache() {
+        let (url, requests_count) = serve_http(&[("*", "/ok", 200)]);
+
+        let site = assert_fs::TempDir::new().unwrap();
+        site.child("index.html")
+            .write_str(&format!("<a href={}/ok>", url))
+            .unwrap();
+
+        for _ in 0..2 {
+            let mut cmd = Command::cargo_bin("hyperlink").unwrap();
+            cmd.current_dir(site.path())
+                .arg(".")
+                .arg("--check-external")
+                .arg("--external-cache=cache.txt");
+            cmd.assert().success();
+        }
+
+        assert_eq!(requests_count.load(Ordering::SeqCst), 1);
+        site.child("cache.txt")
+            .assert(predicate::str::contains(format!("\t200\t{}/ok\n", url)));
+        site.close().unwrap();
+    }
+
     #[test]
     fn test_no_args() {
         let mut cmd = Command::cargo_bin("hyperlink").unwrap();
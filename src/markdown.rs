@@ -1,11 +1,13 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::fs;
+use std::mem;
+use std::ops::Range;
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::Error;
-use pulldown_cmark::{Event, Parser, Tag};
+use pulldown_cmark::{Event, LinkType, Parser, Tag};
 
+use crate::ignore::IgnoreComment;
 use crate::paragraph::ParagraphWalker;
 
 // Note: Keep in sync with html.rs
@@ -16,53 +18,164 @@ pub struct DocumentSource {
     pub path: Arc<PathBuf>,
 }
 
-impl DocumentSource {
-    pub fn new(path: PathBuf) -> Self {
-        DocumentSource {
-            path: Arc::new(path),
-        }
-    }
+/// The text of a markdown file as it is parsed.
+struct SourceText {
+    /// The contents of the file.
+    contents: String,
+    /// The contents without lines starting with `<`, and without the `: ` prefix of definition
+    /// lists.
+    text: String,
+    // line_numbers[0] = 32 ... line 0 ends at `text` offset 32
+    line_numbers: Vec<usize>,
+    /// Offset in `contents` of the text of each line in `text`.
+    line_offsets: Vec<usize>,
+    /// Comments by the `text` offset they were found at. They are not part of `text` itself.
+    ignore_comments: Vec<(usize, IgnoreComment)>,
+}
 
-    pub fn paragraphs<P: ParagraphWalker>(&self) -> Result<Vec<(P::Paragraph, usize)>, Error> {
+impl SourceText {
+    fn new(contents: String) -> Self {
         let mut text = String::new();
-        // line_numbers[0] = 32 ... line 0 ends at `text` offset 32
         let mut line_numbers = Vec::new();
-        for line in BufReader::new(File::open(&*self.path)?).lines() {
-            let line = line?;
-            let mut line = line.as_str();
+        let mut line_offsets = Vec::new();
+        let mut ignore_comments = Vec::new();
+
+        let mut offset = 0;
+        while offset < contents.len() {
+            let line_end = contents[offset..]
+                .find('\n')
+                .map_or(contents.len(), |i| offset + i);
+            let mut line = &contents[offset..line_end];
+            let mut line_offset = offset;
+            offset = line_end + 1;
+
+            if let Some(stripped) = line.strip_suffix('\r') {
+                line = stripped;
+            }
 
             if line.starts_with('<') {
+                if let Some(comment) = IgnoreComment::parse(line) {
+                    ignore_comments.push((text.len(), comment));
+                }
                 continue;
             }
 
             if line.starts_with(": ") {
                 line = &line[2..];
+                line_offset += 2;
             }
 
+            line_offsets.push(line_offset);
             text.push_str(line);
             text.push('\n');
             line_numbers.push(text.len());
         }
 
+        SourceText {
+            contents,
+            text,
+            line_numbers,
+            line_offsets,
+            ignore_comments,
+        }
+    }
+
+    /// The line number that is reported for a paragraph ending at the `text` offset.
+    fn lineno(&self, offset: usize) -> usize {
+        match self.line_numbers.binary_search(&offset) {
+            Ok(i) => i + 1,
+            Err(i) => i + 1,
+        }
+    }
+
+    /// Translate a range in `text` to a range in `contents`, if it does not span multiple lines.
+    fn contents_range(&self, range: Range<usize>) -> Option<Range<usize>> {
+        let i = match self.line_numbers.binary_search(&range.start) {
+            Ok(i) => i + 1,
+            Err(i) => i,
+        };
+        let line_start = if i == 0 { 0 } else { self.line_numbers[i - 1] };
+        if range.end >= *self.line_numbers.get(i)? {
+            return None;
+        }
+
+        let start = self.line_offsets[i] + range.start - line_start;
+        Some(start..start + range.len())
+    }
+}
+
+/// An inline link or image in a markdown file.
+#[derive(Debug, Eq, PartialEq)]
+pub struct SourceLink {
+    /// The line number of the paragraph the link is in, the same as for `paragraphs`.
+    pub lineno: usize,
+    pub dest: String,
+    /// Where the destination is written in the file.
+    pub range: Range<usize>,
+}
+
+impl DocumentSource {
+    pub fn new(path: PathBuf) -> Self {
+        DocumentSource {
+            path: Arc::new(path),
+        }
+    }
+
+    fn read(&self) -> Result<SourceText, Error> {
+        Ok(SourceText::new(fs::read_to_string(&*self.path)?))
+    }
+
+    /// Returns all paragraphs with their line number, and whether problems with links in them are
+    /// suppressed by a comment.
+    pub fn paragraphs<P: ParagraphWalker>(
+        &self,
+    ) -> Result<Vec<(P::Paragraph, usize, bool)>, Error> {
+        let source = self.read()?;
+
         let mut in_paragraph = false;
         let mut walker = P::new();
         let mut rv = Vec::new();
 
-        for (event, range) in Parser::new(&text).into_offset_iter() {
+        let mut ignore_comments = source.ignore_comments.iter().copied().peekable();
+        let mut ignore_next = false;
+        let mut in_ignore_region = false;
+        // Whether an ignore region was active at any point in the current paragraph.
+        let mut paragraph_in_ignore_region = false;
+        macro_rules! apply_comment {
+            ($comment:expr) => {
+                match $comment {
+                    IgnoreComment::Next => ignore_next = true,
+                    IgnoreComment::Start => in_ignore_region = true,
+                    IgnoreComment::End => in_ignore_region = false,
+                }
+            };
+        }
+
+        for (event, range) in Parser::new(&source.text).into_offset_iter() {
             match event {
                 Event::Start(tag) if PARAGRAPH_TAGS.contains(&tag) => {
                     walker.finish_paragraph();
                     in_paragraph = true;
+
+                    // Apply all comments before this paragraph
+                    while let Some(&(offset, comment)) = ignore_comments.peek() {
+                        if offset > range.start {
+                            break;
+                        }
+                        ignore_comments.next();
+                        apply_comment!(comment);
+                    }
+                    paragraph_in_ignore_region = in_ignore_region;
                 }
                 Event::End(tag) if PARAGRAPH_TAGS.contains(&tag) => {
                     let paragraph = walker.finish_paragraph();
                     if in_paragraph {
+                        let ignored = mem::take(&mut ignore_next)
+                            || in_ignore_region
+                            || paragraph_in_ignore_region;
+
                         if let Some(paragraph) = paragraph {
-                            let lineno = match line_numbers.binary_search(&range.end) {
-                                Ok(i) => i + 1,
-                                Err(i) => i + 1,
-                            };
-                            rv.push((paragraph, lineno));
+                            rv.push((paragraph, source.lineno(range.end), ignored));
                         }
                     }
                     in_paragraph = false;
@@ -72,10 +185,126 @@ impl DocumentSource {
                         walker.update(text.as_bytes());
                     }
                 }
+                // Comments that do not start a line, such as within a paragraph. They apply to
+                // the paragraph they are in.
+                Event::Html(html) => {
+                    if let Some(comment) = IgnoreComment::parse(&html) {
+                        apply_comment!(comment);
+                        paragraph_in_ignore_region |= in_ignore_region;
+                    }
+                }
                 _ => {}
             }
         }
 
         Ok(rv)
     }
+
+    /// Returns the contents of the file, and all inline links and images in paragraphs. Links
+    /// whose destination cannot be located in the file, e.g. because of escapes, are skipped.
+    pub fn links(&self) -> Result<(String, Vec<SourceLink>), Error> {
+        let source = self.read()?;
+
+        let mut rv = Vec::new();
+        // Links of the current paragraph, without line number.
+        let mut links = Vec::new();
+
+        for (event, range) in Parser::new(&source.text).into_offset_iter() {
+            match event {
+                Event::Start(Tag::Link(LinkType::Inline, dest, _))
+                | Event::Start(Tag::Image(LinkType::Inline, dest, _)) => {
+                    // The destination follows the link text, possibly followed by a title.
+                    let link = &source.text[range.clone()];
+                    let dest_start = link
+                        .rfind("](")
+                        .and_then(|i| Some(i + link[i..].find(&*dest)?));
+                    if let Some(dest_start) = dest_start {
+                        let start = range.start + dest_start;
+                        if let Some(range) = source.contents_range(start..start + dest.len()) {
+                            links.push((dest.into_string(), range));
+                        }
+                    }
+                }
+                // As with paragraphs, links are only attributed to the innermost paragraph.
+                Event::Start(tag) if PARAGRAPH_TAGS.contains(&tag) => links.clear(),
+                Event::End(tag) if PARAGRAPH_TAGS.contains(&tag) => {
+                    let lineno = source.lineno(range.end);
+                    for (dest, range) in links.drain(..) {
+                        rv.push(SourceLink {
+                            lineno,
+                            dest,
+                            range,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok((source.contents, rv))
+    }
+}
+
+#[test]
+fn test_links() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    let path = dir.path().join("index.md");
+    fs::write(
+        &path,
+        "# Title\r\n\
+         \r\n\
+         <!-- hyperlink-ignore-next -->\r\n\
+         See [install](docs/install.md \"docs/install.md\") and\r\n\
+         ![logo](img/logo.png).\r\n\
+         \r\n\
+         Term\r\n\
+         : [faq](faq.md) [reference][ref]\r\n\
+         \r\n\
+         [ref]: reference.md\r\n",
+    )
+    .unwrap();
+
+    let (contents, links) = DocumentSource::new(path).links().unwrap();
+    let dests: Vec<_> = links
+        .iter()
+        .map(|link| (link.lineno, &contents[link.range.clone()]))
+        .collect();
+    assert_eq!(
+        dests,
+        vec![(4, "docs/install.md"), (4, "img/logo.png"), (7, "faq.md"),]
+    );
+}
+
+#[test]
+fn test_paragraphs_ignore_comments() {
+    use crate::paragraph::ParagraphHasher;
+
+    let dir = assert_fs::TempDir::new().unwrap();
+    let path = dir.path().join("index.md");
+    fs::write(
+        &path,
+        "<!-- hyperlink-ignore-next -->\n\
+         First [link](a.html)\n\
+         \n\
+         Second <!-- hyperlink-ignore-next --> [link](b.html)\n\
+         \n\
+         Third [link](c.html)\n\
+         \n\
+         <!-- hyperlink-ignore-start -->\n\
+         Fourth [link](d.html) <!-- hyperlink-ignore-end -->\n\
+         \n\
+         Fifth [link](e.html)\n",
+    )
+    .unwrap();
+
+    let ignored: Vec<_> = DocumentSource::new(path)
+        .paragraphs::<ParagraphHasher>()
+        .unwrap()
+        .into_iter()
+        .map(|(_, lineno, ignored)| (lineno, ignored))
+        .collect();
+    assert_eq!(
+        ignored,
+        vec![(1, true), (3, true), (5, false), (7, true), (9, false)]
+    );
 }
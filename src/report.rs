@@ -0,0 +1,955 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::env;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Error};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::baseline::{Baseline, BaselineEntry};
+use crate::ignore::site_path;
+
+/// The categories problems are reported in, ordered by severity.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(into = "&'static str", try_from = "String")]
+pub enum ProblemKind {
+    BadLink,
+    BadRedirect,
+    BadTrailingSlash,
+    BadAnchor,
+    HtmlExtension,
+    BadExternalLink,
+    Orphan,
+    Unreachable,
+}
+
+static PROBLEM_KINDS: &[ProblemKind] = &[
+    ProblemKind::BadLink,
+    ProblemKind::BadRedirect,
+    ProblemKind::BadTrailingSlash,
+    ProblemKind::BadAnchor,
+    ProblemKind::HtmlExtension,
+    ProblemKind::BadExternalLink,
+    ProblemKind::Orphan,
+    ProblemKind::Unreachable,
+];
+
+impl FromStr for ProblemKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        PROBLEM_KINDS
+            .iter()
+            .copied()
+            .find(|kind| kind.id() == s)
+            .ok_or_else(|| {
+                let ids: Vec<_> = PROBLEM_KINDS.iter().map(|kind| kind.id()).collect();
+                anyhow!(
+                    "Invalid kind of problem {}, expected one of: {}",
+                    s,
+                    ids.join(", ")
+                )
+            })
+    }
+}
+
+impl From<ProblemKind> for &'static str {
+    fn from(kind: ProblemKind) -> Self {
+        kind.id()
+    }
+}
+
+impl TryFrom<String> for ProblemKind {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self, Error> {
+        s.parse()
+    }
+}
+
+impl ProblemKind {
+    /// Describes a single problem of this kind, as in "error: bad link".
+    fn message(self) -> &'static str {
+        match self {
+            ProblemKind::BadLink | ProblemKind::BadAnchor => "bad link",
+            ProblemKind::BadRedirect => "bad redirect",
+            ProblemKind::BadTrailingSlash => "bad trailing slash",
+            ProblemKind::HtmlExtension => "link with .html extension",
+            ProblemKind::BadExternalLink => "bad external link",
+            ProblemKind::Orphan => "orphaned file",
+            ProblemKind::Unreachable => "unreachable file",
+        }
+    }
+
+    /// Describes multiple problems of this kind, as in "Found 3 bad links".
+    fn plural_message(self) -> &'static str {
+        match self {
+            ProblemKind::BadLink => "bad links",
+            ProblemKind::BadRedirect => "bad redirects",
+            ProblemKind::BadTrailingSlash => "bad trailing slashes",
+            ProblemKind::BadAnchor => "bad anchors",
+            ProblemKind::HtmlExtension => "links with .html extension",
+            ProblemKind::BadExternalLink => "bad external links",
+            ProblemKind::Orphan => "orphaned files",
+            ProblemKind::Unreachable => "unreachable files",
+        }
+    }
+
+    /// Identifier of the kind in machine-readable reports and configuration, which is also what
+    /// it is serialized as.
+    pub fn id(self) -> &'static str {
+        match self {
+            ProblemKind::BadLink => "bad-link",
+            ProblemKind::BadRedirect => "bad-redirect",
+            ProblemKind::BadTrailingSlash => "bad-trailing-slash",
+            ProblemKind::BadAnchor => "bad-anchor",
+            ProblemKind::HtmlExtension => "html-extension",
+            ProblemKind::BadExternalLink => "bad-external-link",
+            ProblemKind::Orphan => "orphan",
+            ProblemKind::Unreachable => "unreachable",
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            ProblemKind::BadLink => "Bad link",
+            ProblemKind::BadRedirect => "Bad redirect",
+            ProblemKind::BadTrailingSlash => "Bad trailing slash",
+            ProblemKind::BadAnchor => "Bad anchor",
+            ProblemKind::HtmlExtension => "Link with .html extension",
+            ProblemKind::BadExternalLink => "Bad external link",
+            ProblemKind::Orphan => "Orphaned file",
+            ProblemKind::Unreachable => "Unreachable file",
+        }
+    }
+
+    /// The prefix of problems of this kind in text output, unless their severity is configured.
+    /// Bad anchors fail the check with a different exit code, but are still shown as errors.
+    fn default_prefix(self) -> &'static str {
+        match self {
+            ProblemKind::BadLink
+            | ProblemKind::BadAnchor
+            | ProblemKind::BadRedirect
+            | ProblemKind::BadTrailingSlash => "error",
+            ProblemKind::HtmlExtension
+            | ProblemKind::BadExternalLink
+            | ProblemKind::Orphan
+            | ProblemKind::Unreachable => "warning",
+        }
+    }
+
+    /// Problems that fail the check (exit code 1) are errors, everything else is a warning.
+    fn default_severity(self) -> Severity {
+        if self.exit_code() == 1 {
+            Severity::Error
+        } else {
+            Severity::Warning
+        }
+    }
+
+    fn exit_code(self) -> i32 {
+        match self {
+            ProblemKind::BadLink | ProblemKind::BadRedirect | ProblemKind::BadTrailingSlash => 1,
+            ProblemKind::BadAnchor | ProblemKind::HtmlExtension => 2,
+            ProblemKind::BadExternalLink => 3,
+            ProblemKind::Orphan | ProblemKind::Unreachable => 4,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    /// How the severity is called in text output, SARIF levels and GitHub Actions commands.
+    fn id(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// Human-readable output, optionally with GitHub Actions annotations.
+    Text,
+    /// A single JSON document with all problems and the summary.
+    Json,
+    /// One JSON object per line, printed as soon as the problem is found.
+    Ndjson,
+    /// SARIF 2.1.0, as consumed by code scanning tools.
+    Sarif,
+    /// JUnit XML, with one testsuite per kind of problem and one testcase per document.
+    Junit,
+    /// GitLab Code Quality report, for annotations in merge requests.
+    Gitlab,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "ndjson" => Ok(Format::Ndjson),
+            "sarif" => Ok(Format::Sarif),
+            "junit" => Ok(Format::Junit),
+            "gitlab" => Ok(Format::Gitlab),
+            _ => Err(anyhow!(
+                "Invalid format {}, expected one of: text, json, ndjson, sarif, junit, gitlab",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Serialize)]
+pub struct Location {
+    pub path: Arc<PathBuf>,
+    pub lineno: Option<usize>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Problem {
+    pub kind: ProblemKind,
+    /// The link as it was resolved, or the target of a redirect rule.
+    pub href: String,
+    /// Additional information such as the response status of an external link.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+    /// The HTML file containing the link, or the configuration file containing the rule.
+    pub path: Arc<PathBuf>,
+    pub lineno: Option<usize>,
+    /// The markdown files and lines the link was probably written in, see `--sources`.
+    pub sources: Vec<Location>,
+    /// Existing hrefs the link may have been meant to point to, best match first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<String>,
+}
+
+impl Problem {
+    /// Where the problem should be fixed: in its markdown sources if known, otherwise in the
+    /// file it was found in.
+    pub fn locations(&self) -> Vec<Location> {
+        if self.sources.is_empty() {
+            vec![Location {
+                path: self.path.clone(),
+                lineno: self.lineno,
+            }]
+        } else {
+            self.sources.clone()
+        }
+    }
+
+    fn text(&self) -> String {
+        let mut text = match self.details {
+            Some(ref details) => format!("{} ({})", self.href, details),
+            None => self.href.clone(),
+        };
+        if !self.suggestions.is_empty() {
+            text.push_str(&format!(" (did you mean {}?)", self.suggestions.join(", ")));
+        }
+        text
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Summary {
+    pub links: usize,
+    pub files: usize,
+    pub documents: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_links: Option<usize>,
+    /// Amount of problems that were not reported because of ignore rules.
+    pub suppressed: usize,
+    /// Amount of problems that were not reported because they are in the baseline.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub baselined: Option<usize>,
+    /// Baseline entries that no longer occur, such that they can be removed from the baseline.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fixed: Option<Vec<BaselineEntry>>,
+    /// Amount of problems by kind, including zero counts for every kind that was checked.
+    pub problems: BTreeMap<ProblemKind, usize>,
+}
+
+type ProblemsByKind = BTreeMap<ProblemKind, BTreeSet<(Option<usize>, String)>>;
+
+/// Line of `--format ndjson`.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum Record<'a> {
+    Problem(&'a Problem),
+    Summary(&'a Summary),
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    problems: &'a [Problem],
+    summary: &'a Summary,
+}
+
+/// Collects problems and prints them in the requested format.
+pub struct Reporter {
+    format: Format,
+    github_actions: bool,
+    /// Severities that differ from the default severity of their kind.
+    severities: BTreeMap<ProblemKind, Severity>,
+    problems: Vec<Problem>,
+    pub summary: Summary,
+    /// The HTML files that were checked, used to report documents without problems.
+    pub documents: Vec<Arc<PathBuf>>,
+    /// Known problems that are not reported.
+    pub baseline: Option<Baseline>,
+    /// Where to write all problems to as new baseline.
+    pub new_baseline: Option<(PathBuf, Baseline)>,
+    /// The sources and static file paths, which GitLab fingerprints are relative to.
+    pub roots: Vec<PathBuf>,
+}
+
+impl Reporter {
+    pub fn new(
+        format: Format,
+        github_actions: bool,
+        severities: BTreeMap<ProblemKind, Severity>,
+    ) -> Self {
+        Reporter {
+            format,
+            github_actions,
+            severities,
+            problems: Vec::new(),
+            summary: Summary::default(),
+            documents: Vec::new(),
+            baseline: None,
+            new_baseline: None,
+            roots: Vec::new(),
+        }
+    }
+
+    /// Include a kind of problem in the summary, even if no such problems are found.
+    pub fn track(&mut self, kind: ProblemKind) {
+        self.summary.problems.entry(kind).or_insert(0);
+    }
+
+    /// Print a progress message. For machine-readable formats, this goes to stderr such that
+    /// stdout can be parsed.
+    pub fn progress(&self, message: fmt::Arguments) {
+        match self.format {
+            Format::Text => println!("{}", message),
+            Format::Json | Format::Ndjson | Format::Sarif | Format::Junit | Format::Gitlab => {
+                eprintln!("{}", message)
+            }
+        }
+    }
+
+    /// Count a problem that is not reported because of an ignore rule.
+    pub fn suppress(&mut self) {
+        self.summary.suppressed += 1;
+    }
+
+    pub fn add(&mut self, problem: Problem) -> Result<(), Error> {
+        if let Some((_, ref mut new_baseline)) = self.new_baseline {
+            new_baseline.insert(&problem);
+        }
+
+        if let Some(ref mut baseline) = self.baseline {
+            if baseline.matches(&problem) {
+                *self.summary.baselined.get_or_insert(0) += 1;
+                return Ok(());
+            }
+        }
+
+        *self.summary.problems.entry(problem.kind).or_insert(0) += 1;
+
+        if self.format == Format::Ndjson {
+            println!("{}", serde_json::to_string(&Record::Problem(&problem))?);
+        }
+
+        self.problems.push(problem);
+        Ok(())
+    }
+
+    /// The problems that are reported, in the order they were found.
+    pub fn problems(&self) -> &[Problem] {
+        &self.problems
+    }
+
+    fn severity(&self, kind: ProblemKind) -> Severity {
+        self.severities
+            .get(&kind)
+            .copied()
+            .unwrap_or_else(|| kind.default_severity())
+    }
+
+    /// The prefix of problems in text output: their severity if it is configured, otherwise as it
+    /// has always been.
+    fn prefix(&self, kind: ProblemKind) -> &'static str {
+        match self.severities.get(&kind) {
+            Some(severity) => severity.id(),
+            None => kind.default_prefix(),
+        }
+    }
+
+    /// The exit code for a kind of problem, taking into account that its severity may be
+    /// overridden.
+    fn exit_code(&self, kind: ProblemKind) -> i32 {
+        match self.severities.get(&kind) {
+            Some(Severity::Error) => 1,
+            Some(Severity::Warning) if kind.exit_code() == 1 => 2,
+            _ => kind.exit_code(),
+        }
+    }
+
+    /// Print the report, and return the exit code for the most severe problem that was found, or
+    /// zero.
+    pub fn finish(mut self) -> Result<i32, Error> {
+        // Links are collected in parallel, make the output independent of thread scheduling.
+        self.problems.sort_by(|a, b| {
+            (&a.path, a.lineno, a.kind, &a.href).cmp(&(&b.path, b.lineno, b.kind, &b.href))
+        });
+
+        if let Some(ref baseline) = self.baseline {
+            self.summary.baselined.get_or_insert(0);
+            self.summary.fixed = Some(baseline.fixed().cloned().collect());
+        }
+
+        if let Some((ref path, ref new_baseline)) = self.new_baseline {
+            new_baseline.save(path)?;
+            self.progress(format_args!(
+                "Wrote {} problems to baseline {}",
+                new_baseline.entries().len(),
+                path.display()
+            ));
+        }
+
+        if self.github_actions {
+            if let Some(path) = env::var_os("GITHUB_STEP_SUMMARY").filter(|path| !path.is_empty()) {
+                self.write_step_summary(Path::new(&path))?;
+            }
+        }
+
+        match self.format {
+            Format::Text => self.print_text()?,
+            Format::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(&JsonReport {
+                    problems: &self.problems,
+                    summary: &self.summary,
+                })?
+            ),
+            Format::Ndjson => println!(
+                "{}",
+                serde_json::to_string(&Record::Summary(&self.summary))?
+            ),
+            Format::Sarif => println!("{}", serde_json::to_string_pretty(&self.sarif())?),
+            Format::Junit => print!("{}", self.junit()),
+            Format::Gitlab => println!("{}", serde_json::to_string_pretty(&self.gitlab())?),
+        }
+
+        Ok(self
+            .summary
+            .problems
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .map(|(kind, _)| self.exit_code(*kind))
+            .min()
+            .unwrap_or(0))
+    }
+
+    /// Problems grouped by the file they should be reported in. The bool in the key is `true` for
+    /// files that problems were found in directly, and `false` for markdown sources.
+    fn problems_by_file(&self) -> BTreeMap<(bool, Arc<PathBuf>), ProblemsByKind> {
+        let mut problems_by_file = BTreeMap::new();
+
+        for problem in &self.problems {
+            let is_raw_file = problem.sources.is_empty();
+            for location in problem.locations() {
+                problems_by_file
+                    .entry((is_raw_file, location.path))
+                    .or_insert_with(BTreeMap::new)
+                    .entry(problem.kind)
+                    .or_insert_with(BTreeSet::new)
+                    .insert((location.lineno, problem.text()));
+            }
+        }
+
+        problems_by_file
+    }
+
+    fn print_text(&self) -> Result<(), Error> {
+        // _is_raw_file is an unused parameter that is only there to control iteration order over
+        // keys. Sort markdown files to the start since otherwise the less valuable annotations on
+        // not checked in files fill up the limit on annotations (tested manually, seems to be 10
+        // right now).
+        for ((_is_raw_file, filepath), problems_by_kind) in self.problems_by_file() {
+            println!("{}", filepath.display());
+
+            for (kind, hrefs) in &problems_by_kind {
+                for (lineno, href) in hrefs {
+                    print_href_error(self.prefix(*kind), kind.message(), href, *lineno);
+                }
+            }
+
+            if self.github_actions {
+                for (kind, hrefs) in &problems_by_kind {
+                    print_github_actions_href_list(*kind, self.severity(*kind), &filepath, hrefs)?;
+                }
+            }
+
+            println!();
+        }
+
+        for (kind, count) in &self.summary.problems {
+            println!("Found {} {}", count, kind.plural_message());
+        }
+
+        if self.summary.suppressed > 0 {
+            println!("Ignored {} problems", self.summary.suppressed);
+        }
+
+        if let Some(baselined) = self.summary.baselined {
+            println!("Accepted {} problems from baseline", baselined);
+        }
+
+        if let Some(ref fixed) = self.summary.fixed {
+            if !fixed.is_empty() {
+                println!(
+                    "Fixed {} problems from baseline, they can be removed from it:",
+                    fixed.len()
+                );
+                for entry in fixed {
+                    println!("  {}", entry);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write a Markdown table of all problems to the job summary of GitHub Actions. Unlike
+    /// annotations, the job summary is not limited in the amount of problems it can show.
+    fn write_step_summary(&self, path: &Path) -> Result<(), Error> {
+        let mut markdown = String::from("## hyperlink\n\n");
+
+        let counts: Vec<_> = self
+            .summary
+            .problems
+            .iter()
+            .map(|(kind, count)| format!("{} {}", count, kind.plural_message()))
+            .collect();
+        markdown.push_str(&format!(
+            "Checked {} links from {} files ({} documents). Found {}.\n",
+            self.summary.links,
+            self.summary.files,
+            self.summary.documents,
+            counts.join(", ")
+        ));
+
+        for ((_is_raw_file, filepath), problems_by_kind) in self.problems_by_file() {
+            let count: usize = problems_by_kind.values().map(BTreeSet::len).sum();
+            markdown.push_str(&format!(
+                "\n### {} ({} {})\n\n| Line | Problem | Link |\n| --- | --- | --- |\n",
+                markdown_code(&relative_path(&filepath)),
+                count,
+                if count == 1 { "problem" } else { "problems" },
+            ));
+
+            for (kind, hrefs) in &problems_by_kind {
+                for (lineno, href) in hrefs {
+                    markdown.push_str(&format!(
+                        "| {} | {} | {} |\n",
+                        lineno.map(|x| x.to_string()).unwrap_or_default(),
+                        kind.title(),
+                        markdown_code(href),
+                    ));
+                }
+            }
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open step summary {}", path.display()))?;
+        file.write_all(markdown.as_bytes())?;
+        Ok(())
+    }
+
+    /// Build a SARIF log with one rule per checked kind of problem. Results point at the markdown
+    /// sources of a link if known, otherwise at the HTML file.
+    fn sarif(&self) -> serde_json::Value {
+        let kinds: Vec<ProblemKind> = self.summary.problems.keys().copied().collect();
+
+        let rules: Vec<_> = kinds
+            .iter()
+            .map(|kind| {
+                json!({
+                    "id": kind.id(),
+                    "name": kind.title(),
+                    "shortDescription": { "text": kind.title() },
+                    "defaultConfiguration": { "level": self.severity(*kind).id() },
+                })
+            })
+            .collect();
+
+        let results: Vec<_> = self
+            .problems
+            .iter()
+            .map(|problem| {
+                let locations: Vec<_> = problem
+                    .locations()
+                    .iter()
+                    .map(|location| {
+                        let mut physical_location = json!({
+                            "artifactLocation": { "uri": relative_uri(&location.path) },
+                        });
+                        if let Some(lineno) = location.lineno {
+                            physical_location["region"] = json!({ "startLine": lineno });
+                        }
+                        json!({ "physicalLocation": physical_location })
+                    })
+                    .collect();
+
+                json!({
+                    "ruleId": problem.kind.id(),
+                    "ruleIndex": kinds.iter().position(|kind| *kind == problem.kind),
+                    "level": self.severity(problem.kind).id(),
+                    "message": {
+                        "text": format!("{}: {}", problem.kind.title(), problem.text()),
+                    },
+                    "locations": locations,
+                })
+            })
+            .collect();
+
+        json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "hyperlink",
+                        "informationUri": "https://github.com/untitaker/hyperlink",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules,
+                    },
+                },
+                "results": results,
+            }],
+        })
+    }
+
+    /// Build a JUnit report. Every checked kind of problem becomes a testsuite, such that e.g. bad
+    /// anchors can be triaged separately from bad links. Every document is a testcase in each
+    /// testsuite, which fails if the document contains problems of that kind.
+    fn junit(&self) -> String {
+        let mut testcases: BTreeSet<&Path> = self.documents.iter().map(|path| &***path).collect();
+        // Problems may also be found in files that are not documents, such as hosting
+        // configuration.
+        testcases.extend(self.problems.iter().map(|problem| &**problem.path));
+
+        let mut problems_by_path: BTreeMap<(ProblemKind, &Path), Vec<&Problem>> = BTreeMap::new();
+        for problem in &self.problems {
+            problems_by_path
+                .entry((problem.kind, &problem.path))
+                .or_insert_with(Vec::new)
+                .push(problem);
+        }
+
+        let total_failures = problems_by_path.len();
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuites name=\"hyperlink\" tests=\"{}\" failures=\"{}\">\n",
+            testcases.len() * self.summary.problems.len(),
+            total_failures,
+        ));
+
+        for kind in self.summary.problems.keys() {
+            let failures = problems_by_path
+                .keys()
+                .filter(|(kind2, _)| kind2 == kind)
+                .count();
+            xml.push_str(&format!(
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">\n",
+                kind.plural_message(),
+                testcases.len(),
+                failures,
+            ));
+
+            for path in &testcases {
+                let name = xml_escape(&path.display().to_string());
+                let problems = match problems_by_path.get(&(*kind, *path)) {
+                    Some(problems) => problems,
+                    None => {
+                        xml.push_str(&format!(
+                            "    <testcase name=\"{}\" classname=\"{}\"/>\n",
+                            name,
+                            kind.id()
+                        ));
+                        continue;
+                    }
+                };
+
+                xml.push_str(&format!(
+                    "    <testcase name=\"{}\" classname=\"{}\">\n",
+                    name,
+                    kind.id()
+                ));
+                xml.push_str(&format!(
+                    "      <failure type=\"{}\" message=\"{} {}\">",
+                    kind.id(),
+                    problems.len(),
+                    kind.plural_message(),
+                ));
+                for problem in problems {
+                    let mut line = problem.text();
+                    for location in &problem.sources {
+                        line.push_str(&format!(" at {}", location.path.display()));
+                        if let Some(lineno) = location.lineno {
+                            line.push_str(&format!(" line {}", lineno));
+                        }
+                    }
+                    if problem.sources.is_empty() {
+                        if let Some(lineno) = problem.lineno {
+                            line.push_str(&format!(" at line {}", lineno));
+                        }
+                    }
+                    xml.push('\n');
+                    xml.push_str(&xml_escape(&line));
+                }
+                xml.push_str("\n      </failure>\n");
+                xml.push_str("    </testcase>\n");
+            }
+
+            xml.push_str("  </testsuite>\n");
+        }
+
+        xml.push_str("</testsuites>\n");
+        xml
+    }
+
+    /// Build a GitLab Code Quality report with one issue per location of a problem.
+    ///
+    /// GitLab compares fingerprints between pipelines to tell new issues from existing ones, so
+    /// they must not depend on line numbers, which change whenever the file is edited above the
+    /// link, nor on the working directory. Instead they are derived from the kind, the path within
+    /// the sources or static file path, the href, and the occurrence of that combination within
+    /// the file.
+    fn gitlab(&self) -> serde_json::Value {
+        let mut issues = Vec::new();
+        for problem in &self.problems {
+            for location in problem.locations() {
+                let root = self
+                    .roots
+                    .iter()
+                    .find(|root| location.path.starts_with(root));
+                let site_path = match root {
+                    Some(root) => site_path(root, &location.path),
+                    None => relative_path(&location.path),
+                };
+                issues.push((
+                    relative_path(&location.path),
+                    site_path,
+                    location.lineno,
+                    problem,
+                ));
+            }
+        }
+        issues.sort_by(
+            |(path, _, lineno, problem), (path2, _, lineno2, problem2)| {
+                (path, lineno, problem.kind, &problem.href).cmp(&(
+                    path2,
+                    lineno2,
+                    problem2.kind,
+                    &problem2.href,
+                ))
+            },
+        );
+
+        let mut occurrences = BTreeMap::new();
+        let issues: Vec<_> = issues
+            .iter()
+            .map(|(path, site_path, lineno, problem)| {
+                let occurrence = occurrences
+                    .entry((site_path, problem.kind, &problem.href))
+                    .or_insert(0);
+                *occurrence += 1;
+
+                let mut hasher = blake3::Hasher::new();
+                for part in &[problem.kind.id(), site_path, &problem.href] {
+                    hasher.update(part.as_bytes());
+                    hasher.update(b"\0");
+                }
+                hasher.update(occurrence.to_string().as_bytes());
+
+                json!({
+                    "description": format!("{}: {}", problem.kind.title(), problem.text()),
+                    "check_name": problem.kind.id(),
+                    "fingerprint": hasher.finalize().to_hex().as_str(),
+                    "severity": match self.severity(problem.kind) {
+                        Severity::Error => "major",
+                        Severity::Warning => "minor",
+                    },
+                    "location": {
+                        "path": path,
+                        "lines": { "begin": lineno.unwrap_or(1) },
+                    },
+                })
+            })
+            .collect();
+
+        json!(issues)
+    }
+}
+
+/// Format text as inline code that can be used in a Markdown table cell.
+fn markdown_code(text: &str) -> String {
+    let text = text.replace('|', "\\|");
+    if text.contains('`') {
+        format!("`` {} ``", text)
+    } else {
+        format!("`{}`", text)
+    }
+}
+
+/// Escape text for XML attributes and elements, for JUnit and GraphML output.
+pub fn xml_escape(text: &str) -> String {
+    String::from_utf8_lossy(&quick_xml::escape::escape(text.as_bytes())).into_owned()
+}
+
+/// Format a path relative to the working directory with forward slashes.
+fn relative_path(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    path.trim_start_matches("./").to_owned()
+}
+
+/// Format a relative path as URI reference, as expected by SARIF consumers.
+fn relative_uri(path: &Path) -> String {
+    let path = relative_path(path);
+
+    let mut uri = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'.' | b'_' | b'~' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+fn print_href_error(
+    prefix: &'static str,
+    message: &'static str,
+    href: &str,
+    lineno: Option<usize>,
+) {
+    if let Some(lineno) = lineno {
+        println!("  {}: {} {} at line {}", prefix, message, href, lineno);
+    } else {
+        println!("  {}: {} {}", prefix, message, href);
+    }
+}
+
+fn print_github_actions_href_list(
+    kind: ProblemKind,
+    severity: Severity,
+    filepath: &Path,
+    hrefs: &BTreeSet<(Option<usize>, String)>,
+) -> Result<(), Error> {
+    // By default, anchors and external links do not fail the check, so they should not show up
+    // as errors either.
+    let command = severity.id();
+    let filepath = filepath.canonicalize()?;
+
+    let mut prev_lineno = None;
+    for (i, (lineno, href)) in hrefs.iter().enumerate() {
+        if prev_lineno != *lineno || i == 0 {
+            // Lines are only known for markdown sources, and there is no information about
+            // columns.
+            let position = match lineno {
+                Some(lineno) => format!("line={},endLine={}", lineno, lineno),
+                None => "line=1".to_owned(),
+            };
+
+            print!(
+                "\n::{} file={},{},title={}::{}:",
+                command,
+                escape_property(&filepath.to_string_lossy()),
+                position,
+                escape_property(kind.title()),
+                kind.plural_message(),
+            );
+        }
+        prev_lineno = *lineno;
+
+        // %0A -- escaped newline
+        //
+        // https://github.community/t/what-is-the-correct-character-escaping-for-workflow-command-values-e-g-echo-xxxx/118465/5
+        print!("%0A  {}", escape_data(href));
+    }
+
+    println!();
+
+    Ok(())
+}
+
+/// Escape the message of a GitHub Actions workflow command.
+fn escape_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a property of a GitHub Actions workflow command, such as `file=`.
+fn escape_property(property: &str) -> String {
+    escape_data(property)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+#[test]
+fn test_relative_uri() {
+    assert_eq!(relative_uri(Path::new("./index.html")), "index.html");
+    assert_eq!(
+        relative_uri(Path::new("src/getting started.md")),
+        "src/getting%20started.md"
+    );
+    assert_eq!(
+        relative_uri(Path::new("docs/a,b:c.md")),
+        "docs/a%2Cb%3Ac.md"
+    );
+}
+
+#[test]
+fn test_escape_github_actions() {
+    assert_eq!(escape_data("100%\nfoo: bar"), "100%25%0Afoo: bar");
+    assert_eq!(escape_property("/tmp/a,b:c.md"), "/tmp/a%2Cb%3Ac.md");
+}
+
+#[test]
+fn test_problem_kind_serde() {
+    for &kind in PROBLEM_KINDS {
+        let serialized = serde_json::to_string(&kind).unwrap();
+        assert_eq!(serialized, format!("\"{}\"", kind.id()));
+        assert_eq!(
+            serde_json::from_str::<ProblemKind>(&serialized).unwrap(),
+            kind
+        );
+    }
+    assert!(serde_json::from_str::<ProblemKind>("\"bad_link\"").is_err());
+}
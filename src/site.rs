@@ -0,0 +1,211 @@
+use std::borrow::Cow;
+
+use anyhow::{anyhow, Error};
+
+/// Where the site is deployed. Used to map links that point to the site by absolute URL or
+/// absolute path back to hrefs relative to the root of the site.
+#[derive(Debug, Default)]
+pub struct SiteUrl {
+    /// The path the site is served under, without trailing slash. Empty if served at the root.
+    path_prefix: String,
+    /// Lowercased hosts (including port) that serve the site.
+    hosts: Vec<String>,
+}
+
+impl SiteUrl {
+    /// Parse a base URL such as `/docs/` or `https://example.com/docs/`.
+    pub fn new(base_url: &str) -> Result<Self, Error> {
+        let mut site_url = SiteUrl::default();
+
+        let path = if base_url.starts_with('/') {
+            base_url
+        } else {
+            let (host, path) = split_url(base_url).ok_or_else(|| {
+                anyhow!(
+                    "Invalid base URL {}, expected an absolute URL or path",
+                    base_url
+                )
+            })?;
+            site_url.hosts.push(host.to_ascii_lowercase());
+            path
+        };
+
+        if path.contains(&['?', '#'][..]) {
+            return Err(anyhow!(
+                "Invalid base URL {}, must not contain a query or fragment",
+                base_url
+            ));
+        }
+
+        site_url.path_prefix = path.trim_end_matches('/').to_owned();
+        Ok(site_url)
+    }
+
+    /// Treat absolute links to another domain as links into the site, e.g. because the site is
+    /// served from multiple domains. The domain may be given with or without scheme.
+    pub fn add_internal_domain(&mut self, domain: &str) -> Result<(), Error> {
+        let host = match split_url(domain) {
+            Some((host, "")) | Some((host, "/")) => host,
+            Some(_) => {
+                return Err(anyhow!(
+                    "Invalid internal domain {}, must not contain a path",
+                    domain
+                ))
+            }
+            None => domain,
+        };
+
+        if !is_valid_host(host) {
+            return Err(anyhow!("Invalid internal domain {}", domain));
+        }
+
+        self.hosts.push(host.to_ascii_lowercase());
+        Ok(())
+    }
+
+    /// Map a link to an href that can be resolved against the document.
+    ///
+    /// Absolute URLs pointing into the site are turned into absolute paths, and the path prefix
+    /// is stripped from absolute paths. Returns `None` for absolute paths outside of the site, as
+    /// those cannot be checked. All other links are returned as-is.
+    pub fn localize<'a>(&self, href: &'a str) -> Option<Cow<'a, str>> {
+        if let Some((host, path)) = split_url(href) {
+            if self.hosts.iter().any(|x| x.eq_ignore_ascii_case(host)) {
+                if let Some(path) = self.strip_prefix(path) {
+                    return Some(path);
+                }
+            }
+
+            return Some(Cow::Borrowed(href));
+        }
+
+        if href.starts_with('/') {
+            return self.strip_prefix(href);
+        }
+
+        Some(Cow::Borrowed(href))
+    }
+
+    /// Strip the path prefix from an absolute path (which may be empty, as in `https://example.com`).
+    fn strip_prefix<'a>(&self, path: &'a str) -> Option<Cow<'a, str>> {
+        let rest = path.strip_prefix(self.path_prefix.as_str())?;
+
+        if rest.starts_with('/') {
+            Some(Cow::Borrowed(rest))
+        } else if rest.is_empty() || rest.starts_with(&['?', '#'][..]) {
+            Some(Cow::Owned(format!("/{}", rest)))
+        } else {
+            // `/docs-old` is not within `/docs`
+            None
+        }
+    }
+}
+
+/// Split an absolute http(s) URL (or protocol-relative URL) into host and path, including query
+/// and fragment.
+fn split_url(url: &str) -> Option<(&str, &str)> {
+    let rest = match url.strip_prefix("//") {
+        Some(rest) => rest,
+        None => url
+            .strip_prefix("https://")
+            .or_else(|| url.strip_prefix("http://"))?,
+    };
+
+    let host_end = rest
+        .find(&['/', '?', '#'][..])
+        .unwrap_or_else(|| rest.len());
+    Some(rest.split_at(host_end))
+}
+
+/// Whether the string is a hostname, optionally with port.
+fn is_valid_host(host: &str) -> bool {
+    let (hostname, port) = match host.find(':') {
+        Some(i) => (&host[..i], Some(&host[i + 1..])),
+        None => (host, None),
+    };
+
+    !hostname.is_empty()
+        && !hostname.contains(&['/', '?', '#'][..])
+        && port.into_iter().all(|port| port.parse::<u16>().is_ok())
+}
+
+#[test]
+fn test_base_path() {
+    let site_url = SiteUrl::new("/docs/").unwrap();
+
+    let localize = |href| site_url.localize(href).map(Cow::into_owned);
+
+    assert_eq!(localize("/docs/install"), Some("/install".to_owned()));
+    assert_eq!(localize("/docs"), Some("/".to_owned()));
+    assert_eq!(localize("/docs#intro"), Some("/#intro".to_owned()));
+    assert_eq!(localize("/docs-old/"), None);
+    assert_eq!(localize("/blog/"), None);
+    assert_eq!(localize("install"), Some("install".to_owned()));
+    assert_eq!(localize("../"), Some("../".to_owned()));
+    assert_eq!(
+        localize("https://example.com/docs/"),
+        Some("https://example.com/docs/".to_owned())
+    );
+}
+
+#[test]
+fn test_base_url() {
+    let site_url = SiteUrl::new("https://Example.com/docs").unwrap();
+
+    let localize = |href| site_url.localize(href).map(Cow::into_owned);
+
+    assert_eq!(
+        localize("https://example.com/docs/install"),
+        Some("/install".to_owned())
+    );
+    assert_eq!(localize("http://EXAMPLE.com/docs"), Some("/".to_owned()));
+    assert_eq!(localize("//example.com/docs/a"), Some("/a".to_owned()));
+    assert_eq!(
+        localize("https://example.com/blog/"),
+        Some("https://example.com/blog/".to_owned())
+    );
+    assert_eq!(
+        localize("https://example.org/docs/"),
+        Some("https://example.org/docs/".to_owned())
+    );
+    assert_eq!(localize("/docs/install"), Some("/install".to_owned()));
+
+    assert!(SiteUrl::new("example.com").is_err());
+    assert!(SiteUrl::new("/docs/#foo").is_err());
+}
+
+#[test]
+fn test_internal_domains() {
+    let mut site_url = SiteUrl::new("/docs/").unwrap();
+    site_url.add_internal_domain("docs.example.com").unwrap();
+    site_url
+        .add_internal_domain("https://www.example.com/")
+        .unwrap();
+    site_url.add_internal_domain("localhost:8000").unwrap();
+
+    let localize = |href| site_url.localize(href).map(Cow::into_owned);
+
+    assert_eq!(
+        localize("https://docs.example.com/docs/install#usage"),
+        Some("/install#usage".to_owned())
+    );
+    assert_eq!(
+        localize("http://www.example.com/docs/"),
+        Some("/".to_owned())
+    );
+    assert_eq!(
+        localize("http://localhost:8000/docs/a"),
+        Some("/a".to_owned())
+    );
+    assert_eq!(
+        localize("https://example.com/docs/"),
+        Some("https://example.com/docs/".to_owned())
+    );
+
+    let mut site_url = SiteUrl::default();
+    assert!(site_url.add_internal_domain("example.com/docs").is_err());
+    assert!(site_url
+        .add_internal_domain("https://example.com/docs")
+        .is_err());
+    assert!(site_url.add_internal_domain("").is_err());
+}
@@ -0,0 +1,302 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Error};
+use serde::Serialize;
+
+use crate::collector::{find_target, StatsCollector};
+use crate::hosting::HostingConfig;
+use crate::html::Document;
+use crate::ignore::site_path;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StatsFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for StatsFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s {
+            "text" => Ok(StatsFormat::Text),
+            "json" => Ok(StatsFormat::Json),
+            _ => Err(anyhow!("Invalid format {}, expected one of: text, json", s)),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Serialize)]
+pub struct PageStats {
+    /// Relative to the static file path.
+    pub path: String,
+    /// Amount of other documents linking to this one.
+    pub in_degree: usize,
+    /// Amount of other files this document links to.
+    pub out_degree: usize,
+    /// Amount of clicks needed to get here from the index page, if it can be reached at all.
+    pub depth: Option<usize>,
+}
+
+/// Statistics about the documents and links of a site.
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub documents: usize,
+    pub assets: usize,
+    pub links: usize,
+    pub unique_hrefs: usize,
+    /// The largest amount of clicks needed to reach a document from the index page.
+    pub max_depth: Option<usize>,
+    pub assets_by_extension: BTreeMap<String, usize>,
+    /// Documents sorted by in-degree, most linked first.
+    pub most_linked: Vec<String>,
+    pub without_outgoing_links: Vec<String>,
+    pub pages: Vec<PageStats>,
+}
+
+impl Stats {
+    /// Links within a document, such as to its own anchors, are not counted for in- and
+    /// out-degree. Every other document or file is counted once, no matter how often it is linked
+    /// to. Links are resolved with the same fallbacks as when checking them. `top` is how many
+    /// documents to list as most linked.
+    pub fn new(
+        collector: &StatsCollector,
+        files: &[Document],
+        documents: &[Arc<PathBuf>],
+        base_path: &Path,
+        hosting: &HostingConfig,
+        top: usize,
+    ) -> Self {
+        let documents: BTreeSet<&Path> = documents.iter().map(|path| path.as_path()).collect();
+        let files_by_href: BTreeMap<&str, &Path> = files
+            .iter()
+            .map(|file| (file.href().0, file.path.as_path()))
+            .collect();
+
+        let mut edges: BTreeSet<(&Path, &Path)> = BTreeSet::new();
+        for (path, href) in &collector.links {
+            if let Some(&target) = find_target(href, hosting, |href| files_by_href.get(href)) {
+                if target != path.as_path() {
+                    edges.insert((path.as_path(), target));
+                }
+            }
+        }
+
+        let mut in_degree: BTreeMap<&Path, usize> = BTreeMap::new();
+        let mut out_degree: BTreeMap<&Path, usize> = BTreeMap::new();
+        let mut adjacent: BTreeMap<&Path, Vec<&Path>> = BTreeMap::new();
+        for &(source, target) in &edges {
+            *in_degree.entry(target).or_insert(0) += 1;
+            *out_degree.entry(source).or_insert(0) += 1;
+            if documents.contains(target) {
+                adjacent.entry(source).or_insert_with(Vec::new).push(target);
+            }
+        }
+
+        // Breadth-first search from the index page, which is served under the empty href.
+        let mut depths: BTreeMap<&Path, usize> = BTreeMap::new();
+        let mut queue = VecDeque::new();
+        if let Some(&index) = files_by_href.get("") {
+            depths.insert(index, 0);
+            queue.push_back(index);
+        }
+        while let Some(path) = queue.pop_front() {
+            let depth = depths[path];
+            for &target in adjacent.get(path).into_iter().flatten() {
+                if !depths.contains_key(target) {
+                    depths.insert(target, depth + 1);
+                    queue.push_back(target);
+                }
+            }
+        }
+
+        let mut assets_by_extension = BTreeMap::new();
+        for file in files {
+            if !documents.contains(file.path.as_path()) {
+                let extension = file
+                    .path
+                    .extension()
+                    .and_then(OsStr::to_str)
+                    .map(str::to_lowercase)
+                    .unwrap_or_default();
+                *assets_by_extension.entry(extension).or_insert(0) += 1;
+            }
+        }
+
+        let pages: Vec<PageStats> = documents
+            .iter()
+            .map(|&path| PageStats {
+                path: site_path(base_path, path),
+                in_degree: in_degree.get(path).copied().unwrap_or(0),
+                out_degree: out_degree.get(path).copied().unwrap_or(0),
+                depth: depths.get(path).copied(),
+            })
+            .collect();
+
+        let mut most_linked: Vec<&PageStats> =
+            pages.iter().filter(|page| page.in_degree > 0).collect();
+        // Stable sort, such that documents with the same in-degree stay sorted by path.
+        most_linked.sort_by_key(|page| Reverse(page.in_degree));
+        let most_linked = most_linked
+            .into_iter()
+            .take(top)
+            .map(|page| page.path.clone())
+            .collect();
+
+        let without_outgoing_links = pages
+            .iter()
+            .filter(|page| page.out_degree == 0)
+            .map(|page| page.path.clone())
+            .collect();
+
+        Stats {
+            documents: documents.len(),
+            assets: files.len() - documents.len(),
+            links: collector.used_link_count,
+            unique_hrefs: collector.hrefs.len(),
+            max_depth: depths.values().copied().max(),
+            assets_by_extension,
+            most_linked,
+            without_outgoing_links,
+            pages,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+
+        text.push_str(&format!("{:<14} {}\n", "Documents", self.documents));
+        text.push_str(&format!("{:<14} {}\n", "Assets", self.assets));
+        text.push_str(&format!("{:<14} {}\n", "Links", self.links));
+        text.push_str(&format!("{:<14} {}\n", "Unique hrefs", self.unique_hrefs));
+        text.push_str(&format!(
+            "{:<14} {}\n",
+            "Max depth",
+            format_depth(self.max_depth)
+        ));
+
+        if !self.assets_by_extension.is_empty() {
+            text.push_str("\nAssets by extension\n");
+            for (extension, count) in &self.assets_by_extension {
+                let extension = if extension.is_empty() {
+                    "(none)"
+                } else {
+                    extension
+                };
+                text.push_str(&format!("  {:<12} {}\n", extension, count));
+            }
+        }
+
+        if !self.most_linked.is_empty() {
+            text.push_str("\nMost linked documents\n");
+            for path in &self.most_linked {
+                text.push_str(&format!("  {}\n", path));
+            }
+        }
+
+        if !self.without_outgoing_links.is_empty() {
+            text.push_str("\nDocuments without outgoing links\n");
+            for path in &self.without_outgoing_links {
+                text.push_str(&format!("  {}\n", path));
+            }
+        }
+
+        text.push_str(&format!(
+            "\n{:>6} {:>6} {:>6}  Document\n",
+            "In", "Out", "Depth"
+        ));
+        for page in &self.pages {
+            text.push_str(&format!(
+                "{:>6} {:>6} {:>6}  {}\n",
+                page.in_degree,
+                page.out_degree,
+                format_depth(page.depth),
+                page.path
+            ));
+        }
+
+        text
+    }
+}
+
+fn format_depth(depth: Option<usize>) -> String {
+    match depth {
+        Some(depth) => depth.to_string(),
+        None => "-".to_owned(),
+    }
+}
+
+#[test]
+fn test_stats() {
+    use crate::collector::LinkCollector;
+    use crate::html::{Href, Link, LinkTag, UsedLink};
+
+    let base_path = Path::new("public");
+    let mut hosting = HostingConfig::default();
+    hosting.clean_urls = true;
+    let files: Vec<_> = [
+        "index.html",
+        "a.html",
+        "b/index.html",
+        "c.html",
+        "logo.PNG",
+        "CNAME",
+    ]
+    .iter()
+    .map(|path| Document::new(base_path, &base_path.join(path)).with_clean_url())
+    .collect();
+    let documents: Vec<_> = files[..4].iter().map(|file| file.path.clone()).collect();
+
+    let mut collector: StatsCollector = LinkCollector::<()>::new();
+    for (from, href) in &[
+        (0, "a.html"),
+        (0, "a#intro"),
+        (0, "logo.PNG"),
+        (0, ""),
+        (1, "b/"),
+        (1, "logo.PNG"),
+        (2, "a"),
+        (2, "missing.html"),
+    ] {
+        collector.ingest(Link::Uses(UsedLink {
+            href: Href(href),
+            path: files[*from].path.clone(),
+            paragraph: None::<()>,
+            tag: LinkTag {
+                element: "a",
+                attribute: "href",
+            },
+            ignored: false,
+        }));
+    }
+
+    let stats = Stats::new(&collector, &files, &documents, base_path, &hosting, 1);
+    assert_eq!(stats.documents, 4);
+    assert_eq!(stats.assets, 2);
+    assert_eq!(stats.links, 8);
+    assert_eq!(stats.unique_hrefs, 7);
+    assert_eq!(stats.max_depth, Some(2));
+    assert_eq!(stats.assets_by_extension.get("png"), Some(&1));
+    assert_eq!(stats.assets_by_extension.get(""), Some(&1));
+    assert_eq!(stats.most_linked, vec!["a.html".to_owned()]);
+    assert_eq!(stats.without_outgoing_links, vec!["c.html".to_owned()]);
+
+    let page = |path: &str| stats.pages.iter().find(|page| page.path == path).unwrap();
+    assert_eq!(
+        page("index.html"),
+        &PageStats {
+            path: "index.html".to_owned(),
+            in_degree: 0,
+            out_degree: 2,
+            depth: Some(0),
+        }
+    );
+    assert_eq!(page("a.html").in_degree, 2);
+    assert_eq!(page("b/index.html").depth, Some(2));
+    assert_eq!(page("c.html").depth, None);
+}
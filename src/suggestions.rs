@@ -0,0 +1,197 @@
+use std::cmp::{max, min};
+
+/// Find the hrefs among `candidates` that a broken link most likely meant, best match first.
+///
+/// Candidates match if they only differ in case, in `.html` extension or `index.html`, if their
+/// path is within a small edit distance, or if they have the same filename in another directory.
+/// The anchor of `href`, if any, is kept.
+pub fn suggest_hrefs<'a>(
+    href: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+    limit: usize,
+) -> Vec<String> {
+    let (path, anchor) = split_anchor(href);
+    let key = path_key(path);
+    let segments: Vec<&str> = key.split('/').collect();
+    let filename = segments.last().copied().unwrap_or("");
+    let threshold = max(1, key.chars().count() / 3);
+
+    let mut matches = Vec::new();
+    for candidate in candidates {
+        if candidate == path {
+            continue;
+        }
+
+        let candidate_key = path_key(candidate);
+        let candidate_segments: Vec<&str> = candidate_key.split('/').collect();
+        let same_filename = !filename.is_empty() && candidate_segments.last() == Some(&filename);
+
+        // Cheap check before computing the distance, most candidates are not even close.
+        let length_difference =
+            max(key.len(), candidate_key.len()) - min(key.len(), candidate_key.len());
+        if !same_filename && length_difference > threshold {
+            continue;
+        }
+
+        let distance = segment_distance(&segments, &candidate_segments);
+        if same_filename || distance <= threshold {
+            matches.push((distance, !same_filename, candidate));
+        }
+    }
+
+    matches.sort();
+    matches
+        .into_iter()
+        .take(limit)
+        .map(|(_, _, candidate)| format!("{}{}", candidate, anchor))
+        .collect()
+}
+
+/// Find the anchors among `candidates` that a broken link to the anchor `anchor` most likely
+/// meant, best match first.
+pub fn suggest_anchors<'a>(
+    anchor: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+    limit: usize,
+) -> Vec<&'a str> {
+    let key = anchor.to_lowercase();
+    let threshold = max(1, key.chars().count() / 3);
+
+    let mut matches: Vec<_> = candidates
+        .into_iter()
+        .filter(|&candidate| candidate != anchor)
+        .map(|candidate| (levenshtein(&key, &candidate.to_lowercase()), candidate))
+        .filter(|&(distance, _)| distance <= threshold)
+        .collect();
+
+    matches.sort();
+    matches
+        .into_iter()
+        .take(limit)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+fn split_anchor(href: &str) -> (&str, &str) {
+    href.split_at(href.find('#').unwrap_or_else(|| href.len()))
+}
+
+/// Normalize the path of an href, such that all ways to link to the same document compare as
+/// equal: `Docs/Install.html`, `docs/install/` and `docs/install/index.html`.
+fn path_key(path: &str) -> String {
+    let mut key = path.to_lowercase();
+
+    for suffix in &["index.html", "index.htm"] {
+        if key == *suffix || key.ends_with(&format!("/{}", suffix)) {
+            key.truncate(key.len() - suffix.len());
+            break;
+        }
+    }
+
+    while key.ends_with('/') {
+        key.pop();
+    }
+
+    for suffix in &[".html", ".htm"] {
+        if key.ends_with(suffix) {
+            key.truncate(key.len() - suffix.len());
+            break;
+        }
+    }
+
+    key
+}
+
+/// Edit distance between two paths, where whole segments are inserted or removed, and segments
+/// that are substituted cost their own edit distance.
+fn segment_distance(a: &[&str], b: &[&str]) -> usize {
+    let segment_cost = |segment: &str| segment.chars().count() + 1;
+
+    let mut previous: Vec<usize> = Vec::with_capacity(b.len() + 1);
+    previous.push(0);
+    for segment in b {
+        previous.push(previous[previous.len() - 1] + segment_cost(segment));
+    }
+
+    for segment_a in a {
+        let mut current = Vec::with_capacity(b.len() + 1);
+        current.push(previous[0] + segment_cost(segment_a));
+        for (j, segment_b) in b.iter().enumerate() {
+            current.push(min(
+                previous[j] + levenshtein(segment_a, segment_b),
+                min(
+                    previous[j + 1] + segment_cost(segment_a),
+                    current[j] + segment_cost(segment_b),
+                ),
+            ));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, char_a) in a.chars().enumerate() {
+        let mut current = Vec::with_capacity(b.len() + 1);
+        current.push(i + 1);
+        for (j, &char_b) in b.iter().enumerate() {
+            let substitution = previous[j] + if char_a == char_b { 0 } else { 1 };
+            current.push(min(substitution, min(previous[j + 1], current[j]) + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[test]
+fn test_suggest_hrefs() {
+    let candidates = [
+        "",
+        "docs/install",
+        "docs/installation",
+        "docs/Usage.html",
+        "guide/faq.html",
+        "img/logo.png",
+        "blog",
+    ];
+    let suggest = |href| suggest_hrefs(href, candidates.iter().copied(), 2);
+
+    assert_eq!(suggest("docs/instal"), vec!["docs/install"]);
+    assert_eq!(
+        suggest("docs/install.html#usage"),
+        vec!["docs/install#usage"]
+    );
+    assert_eq!(suggest("docs/usage.html"), vec!["docs/Usage.html"]);
+    assert_eq!(suggest("faq.html"), vec!["guide/faq.html"]);
+    assert_eq!(suggest("images/logo.png"), vec!["img/logo.png"]);
+    assert_eq!(suggest("blog/index.html"), vec!["blog"]);
+    assert_eq!(suggest("about.html"), Vec::<String>::new());
+    assert_eq!(
+        suggest_hrefs("docs/instal", candidates.iter().copied(), 0),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn test_suggest_anchors() {
+    let candidates = ["usage", "installation", "Install", "faq"];
+    let suggest = |anchor| suggest_anchors(anchor, candidates.iter().copied(), 3);
+
+    assert_eq!(suggest("install"), vec!["Install"]);
+    assert_eq!(suggest("instalation"), vec!["installation"]);
+    assert_eq!(suggest("usages"), vec!["usage"]);
+    assert_eq!(suggest("license"), Vec::<&str>::new());
+}
+
+#[test]
+fn test_segment_distance() {
+    assert_eq!(segment_distance(&["a", "b"], &["a", "b"]), 0);
+    assert_eq!(segment_distance(&["a", "bc"], &["a", "b"]), 1);
+    assert_eq!(segment_distance(&["faq"], &["guide", "faq"]), 6);
+    assert_eq!(levenshtein("kitten", "sitting"), 3);
+}